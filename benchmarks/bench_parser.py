@@ -0,0 +1,80 @@
+"""Time FIGAROParser parsing and flow extraction on synthetic FIGARO file pairs.
+
+Each size reports the best of several cold runs as a baseline for
+performance changes.
+
+Run from the repository root with: python -m benchmarks.bench_parser
+"""
+import os
+import tempfile
+import time
+from src.parser import FIGAROParser, clear_tsv_cache
+
+def write_synthetic_files(data_dir: str, sectors: int, geos: int, years: int) -> None:
+    """Write an imports/exports pair with sectors x geos series and `years` year columns."""
+    figaro_dir = os.path.join(data_dir, 'figaro')
+    os.makedirs(figaro_dir, exist_ok=True)
+    
+    header = "freq,nace_r2,c_exp,unit,geo\t" + "\t".join(str(2000 + y) for y in range(years)) + "\n"
+    rows = [
+        f"A,C{s:03d},EXP_GO,MIO_EUR,G{g:03d}\t" + "\t".join(f"{(s + 1) * (g + 1) + y}.5" for y in range(years))
+        for s in range(sectors)
+        for g in range(geos)
+    ]
+    for name in ('estat_naio_10_fgti.tsv', 'estat_naio_10_fgte.tsv'):
+        with open(os.path.join(figaro_dir, name), 'w') as f:
+            f.write(header + "\n".join(rows) + "\n")
+
+# (name, sectors, geos, years) of the synthetic file pairs to time
+SIZES = [
+    ('small', 20, 10, 5),
+    ('medium', 200, 50, 20),
+    ('large', 500, 100, 30),
+]
+REPEATS = 5
+
+def best_time(func, repeats: int = REPEATS) -> float:
+    """Get the fastest of several timed calls, clearing the frame cache before each."""
+    timings = []
+    for _ in range(repeats):
+        clear_tsv_cache()
+        start = time.perf_counter()
+        func()
+        timings.append(time.perf_counter() - start)
+    return min(timings)
+
+def bench_size(name: str, sectors: int, geos: int, years: int) -> None:
+    """Time parsing and flow extraction on one synthetic file pair."""
+    with tempfile.TemporaryDirectory() as tmpdir:
+        write_synthetic_files(tmpdir, sectors=sectors, geos=geos, years=years)
+        parser = FIGAROParser(tmpdir)
+        year = 2000 + years // 2
+        
+        parse = best_time(lambda: parser._read_and_clean_df(parser.imports_file))
+        mapped_parser = FIGAROParser(tmpdir, memory_map=True)
+        mapped = best_time(lambda: mapped_parser._read_and_clean_df(mapped_parser.imports_file))
+        cold = best_time(lambda: parser.get_flow_data(year))
+        
+        start = time.perf_counter()
+        flows = parser.get_flow_data(year)
+        warm = time.perf_counter() - start
+        
+        print(
+            f"{name:>6} ({sectors * geos} series x {years} years): parse {parse:.3f}s "
+            f"({mapped:.3f}s memory-mapped), "
+            f"get_flow_data {len(flows)} flows cold {cold:.3f}s, cached {warm:.3f}s"
+        )
+        
+        packed_mb = parser.estimated_memory(parser.imports_file, categorical=False) / 1e6
+        categorized_mb = parser.estimated_memory(parser.imports_file) / 1e6
+        print(
+            f"{'':>6} cached frame: {parser.series_count(parser.imports_file)} series, "
+            f"{packed_mb:.1f} MB with packed metadata, {categorized_mb:.1f} MB categorical"
+        )
+
+def main() -> None:
+    for size in SIZES:
+        bench_size(*size)
+
+if __name__ == '__main__':
+    main()
@@ -1 +1,918 @@
 # Economic data processing
+from typing import Dict, List, Optional, Tuple
+import logging
+import math
+import os
+import re
+import pandas as pd
+from src.errors import MissingYearError
+from src.geo_names import PARTNER_GROUPS, geo_name, partner_group
+from src.parser import FIGAROParser, IMPORTS_FLOW, EXPORTS_FLOW, PERIOD_PATTERNS, exact_sum
+
+logger = logging.getLogger(__name__)
+
+# How smoothed_time_series treats years too close to the ends for a full window
+SMOOTHING_EDGES = ('shrink', 'nan')
+
+# Fewest years both flows must report for a meaningful correlation
+MIN_CORRELATION_POINTS = 3
+
+# Label of the bucket other_threshold merges small sectors into
+OTHER_BUCKET = 'Other'
+
+def _totals_by(
+    parser: FIGAROParser, file_path: str, year: int, key: str = 'nace_r2', unit_filter: Optional[str] = None
+) -> pd.Series:
+    """Sum the non-NaN values of a flow file per metadata field (NACE sector by default).
+    
+    A file mixing units raises a MixedUnitsError unless unit_filter keeps
+    only one of them.
+    
+    Sums go through exact_sum, as in every aggregation here: whole numbers
+    are added exactly and floats are rounded once, so values spanning many
+    orders of magnitude do not drift the way accumulating floats does.
+    """
+    return _year_frame(parser, file_path, year, unit_filter).groupby(key)['value'].agg(exact_sum)
+
+def _year_frame(parser: FIGAROParser, file_path: str, year: int, unit_filter: Optional[str] = None) -> pd.DataFrame:
+    """Get the rows of a year with a value, checked to share a single unit (see FIGAROParser.single_unit)."""
+    df = parser.get_year_frame(file_path, year).dropna(subset=['value'])
+    return parser.single_unit(df, os.path.basename(file_path), unit_filter)
+
+def _flow_files(parser: FIGAROParser) -> List[Tuple[str, str]]:
+    """Get the (file path, flow type) pairs of a parser."""
+    return [(parser.imports_file, IMPORTS_FLOW), (parser.exports_file, EXPORTS_FLOW)]
+
+def _flow_file(parser: FIGAROParser, flow_type: str) -> str:
+    """Get the path of the file holding a flow type."""
+    file_paths = dict((flow, path) for path, flow in _flow_files(parser))
+    if flow_type not in file_paths:
+        raise ValueError(f"Unknown flow type: {flow_type}; expected one of {', '.join(file_paths)}")
+    return file_paths[flow_type]
+
+def _yearly_totals(
+    parser: FIGAROParser, file_path: str, years: List[int], key: str = 'nace_r2', unit_filter: Optional[str] = None
+) -> pd.DataFrame:
+    """Sum several years of a flow file per metadata field from a single parse.
+    
+    Groups without any non-NaN value for a year hold NaN rather than zero.
+    Units are checked over all rows as in _totals_by.
+    """
+    df = parser.read_frame(file_path, years=years)
+    year_columns = [str(year) for year in years]
+    
+    missing = [col for col in year_columns if col not in df.columns]
+    if missing:
+        raise MissingYearError(f"Year(s) {', '.join(missing)} not found in {os.path.basename(file_path)}")
+    
+    df = parser.single_unit(df.dropna(subset=year_columns, how='all'), os.path.basename(file_path), unit_filter)
+    return df.groupby(key)[year_columns].agg(exact_sum, min_count=1)
+
+def _join_sector_totals(
+    parser: FIGAROParser,
+    year: int,
+    key: str = 'nace_r2',
+    inner_only: bool = False,
+    unit_filter: Optional[str] = None
+) -> pd.DataFrame:
+    """Outer-join (or with inner_only inner-join) import and export totals per NACE sector or another metadata field.
+    
+    Both files must share a single unit, since the totals are set against
+    each other.
+    """
+    imports = _year_frame(parser, parser.imports_file, year, unit_filter)
+    exports = _year_frame(parser, parser.exports_file, year, unit_filter)
+    parser.single_unit(
+        pd.concat([imports, exports]),
+        f"{os.path.basename(parser.imports_file)} with {os.path.basename(parser.exports_file)}"
+    )
+    return pd.concat(
+        [
+            imports.groupby(key)['value'].agg(exact_sum).rename('imports'),
+            exports.groupby(key)['value'].agg(exact_sum).rename('exports')
+        ],
+        axis=1,
+        join='inner' if inner_only else 'outer'
+    )
+
+def _keep_geos(totals: pd.Series, geos: Optional[List[str]]) -> pd.Series:
+    """Keep the totals of a set of geo codes, or all of them for None; unknown codes are ignored."""
+    if geos is None:
+        return totals
+    wanted = set(geos)
+    unknown = wanted.difference(totals.index)
+    if unknown:
+        logger.debug("Ignoring geo codes without data: %s", ', '.join(sorted(unknown)))
+    return totals[totals.index.isin(wanted)]
+
+def _sign(value: float) -> int:
+    """Get -1, 0 or 1 for the direction of a value."""
+    return (value > 0) - (value < 0)
+
+def _bucket_other(shares: pd.Series, other_threshold: Optional[float]) -> pd.Series:
+    """Merge the shares below other_threshold percent into a trailing OTHER_BUCKET entry.
+    
+    Shares exactly at the threshold are kept, and a single small sector keeps
+    its own code since merging it would only rename it.
+    """
+    if other_threshold is None:
+        return shares
+    small = shares < other_threshold
+    if small.sum() < 2:
+        return shares
+    return pd.concat([shares[~small], pd.Series({OTHER_BUCKET: exact_sum(shares[small])})])
+
+def compare_datasets(
+    parser: FIGAROParser, old_path: str, new_path: str, year: int, unit_filter: Optional[str] = None
+) -> List[Tuple[str, float, float, float]]:
+    """Get (nace, old_value, new_value, delta) per NACE sector between two releases of a flow file.
+    
+    Sectors present in only one file have NaN on the missing side and as delta.
+    Results are sorted by NACE code.
+    """
+    totals = pd.concat(
+        [
+            _totals_by(parser, old_path, year, unit_filter=unit_filter).rename('old'),
+            _totals_by(parser, new_path, year, unit_filter=unit_filter).rename('new')
+        ],
+        axis=1
+    ).astype(float).sort_index()
+    delta = totals['new'] - totals['old']
+    
+    return [
+        (nace, float(totals.at[nace, 'old']), float(totals.at[nace, 'new']), float(delta[nace]))
+        for nace in totals.index
+    ]
+
+def net_flows(
+    parser: FIGAROParser,
+    year: int,
+    inner_only: bool = False,
+    absolute: bool = False,
+    unit_filter: Optional[str] = None
+) -> List[Tuple]:
+    """Get the trade balance (exports - imports) per NACE sector.
+    
+    Sectors present in only one file are kept with the missing side counted
+    as zero, or left out with inner_only. Results are sorted by absolute
+    balance, largest first.
+    
+    With absolute, each balance is returned as (nace, magnitude, sign) with
+    a sign of -1, 0 or 1, e.g. to size bubbles and color them by direction.
+    
+    Files mixing units, or an imports and an exports file in different
+    units, raise a MixedUnitsError unless unit_filter (e.g. 'MIO_EUR') keeps
+    a single one; the same holds for the other functions here taking a
+    unit_filter.
+    """
+    totals = _join_sector_totals(parser, year, inner_only=inner_only, unit_filter=unit_filter)
+    balance = totals['exports'].fillna(0) - totals['imports'].fillna(0)
+    
+    flows = sorted(
+        [(nace, float(value)) for nace, value in balance.items()],
+        key=lambda item: abs(item[1]),
+        reverse=True
+    )
+    if absolute:
+        return [(nace, abs(value), _sign(value)) for nace, value in flows]
+    return flows
+
+def flows_per_gdp(
+    parser: FIGAROParser, year: int, gdp_by_geo: Dict[str, float], unit_filter: Optional[str] = None
+) -> List[Tuple[str, str, float]]:
+    """Get each country's imports and exports as a percentage of its GDP.
+    
+    gdp_by_geo maps geo codes to GDP in the unit of the flows. Countries
+    without a GDP are skipped with a logged warning.
+    """
+    flows = []
+    skipped = set()
+    for geo, flow_type, value in flows_by_geo(parser, year, unit_filter=unit_filter):
+        if geo not in gdp_by_geo:
+            skipped.add(geo)
+            continue
+        flows.append((geo, flow_type, value / gdp_by_geo[geo] * 100))
+    
+    if skipped:
+        logger.warning("Skipping geo codes without a GDP: %s", ', '.join(sorted(skipped)))
+    return flows
+
+def flows_per_capita(
+    parser: FIGAROParser, year: int, population_by_geo: Dict[str, float], unit_filter: Optional[str] = None
+) -> List[Tuple[str, str, float]]:
+    """Get each country's imports and exports per person.
+    
+    population_by_geo maps geo codes to population, which must be positive.
+    Values stay in the unit of the flows (e.g. MIO_EUR per person).
+    Countries without a population are skipped with a logged warning.
+    """
+    invalid = sorted(geo for geo, population in population_by_geo.items() if not population > 0)
+    if invalid:
+        raise ValueError(f"Populations must be positive; got {', '.join(invalid)}")
+    
+    flows = []
+    skipped = set()
+    for geo, flow_type, value in flows_by_geo(parser, year, unit_filter=unit_filter):
+        if geo not in population_by_geo:
+            skipped.add(geo)
+            continue
+        flows.append((geo, flow_type, value / population_by_geo[geo]))
+    
+    if skipped:
+        logger.warning("Skipping geo codes without a population: %s", ', '.join(sorted(skipped)))
+    return flows
+
+def paired_flows(
+    parser: FIGAROParser,
+    year: int,
+    fill_zero: bool = False,
+    inner_only: bool = False,
+    unit_filter: Optional[str] = None
+) -> List[Tuple[str, float, float]]:
+    """Get (nace, imports, exports) per NACE sector for side-by-side bars.
+    
+    A side missing from its file is NaN, or 0.0 with fill_zero. inner_only
+    keeps only the sectors found in both files. Sectors are sorted by total
+    trade volume, largest first.
+    """
+    totals = _join_sector_totals(parser, year, inner_only=inner_only, unit_filter=unit_filter)
+    if fill_zero:
+        totals = totals.fillna(0)
+    volume = totals['imports'].fillna(0) + totals['exports'].fillna(0)
+    
+    return [
+        (nace, float(totals.at[nace, 'imports']), float(totals.at[nace, 'exports']))
+        for nace in volume.sort_values(ascending=False, kind='stable').index
+    ]
+
+def full_sector_breakdown(
+    parser: FIGAROParser, year: int, unit_filter: Optional[str] = None
+) -> List[Tuple[str, float, float, float]]:
+    """Get (nace, imports, exports, balance) per NACE sector from one outer join.
+    
+    A side missing from its file is NaN, telling "no data" apart from zero
+    trade, but counts as zero in the balance (exports - imports). Sectors
+    are sorted by total trade volume, largest first.
+    """
+    totals = _join_sector_totals(parser, year, unit_filter=unit_filter)
+    balance = totals['exports'].fillna(0) - totals['imports'].fillna(0)
+    volume = totals['imports'].fillna(0) + totals['exports'].fillna(0)
+    
+    return [
+        (nace, float(totals.at[nace, 'imports']), float(totals.at[nace, 'exports']), float(balance[nace]))
+        for nace in volume.sort_values(ascending=False, kind='stable').index
+    ]
+
+def flows_by_geo(
+    parser: FIGAROParser,
+    year: int,
+    with_geo_names: bool = False,
+    geos: Optional[List[str]] = None,
+    unit_filter: Optional[str] = None
+) -> List[Tuple]:
+    """Get import and export totals per country, summed over all NACE sectors.
+    
+    With with_geo_names each tuple gains a fourth item, the country name
+    (e.g. 'Greece' for EL); unknown codes are named after themselves. geos
+    keeps only the listed countries, ignoring codes without data.
+    """
+    flows = []
+    for file_path, flow_type in _flow_files(parser):
+        totals = _keep_geos(_totals_by(parser, file_path, year, key='geo', unit_filter=unit_filter), geos)
+        flows.extend((geo, flow_type, float(value)) for geo, value in totals.items())
+    
+    if with_geo_names:
+        flows = [flow + (geo_name(flow[0]),) for flow in flows]
+    
+    return sorted(flows, key=lambda flow: (flow[0], flow[1]))
+
+def balance_by_geo(
+    parser: FIGAROParser, year: int, geos: Optional[List[str]] = None, unit_filter: Optional[str] = None
+) -> List[Tuple[str, float]]:
+    """Get the trade balance (exports - imports) per country over all NACE sectors.
+    
+    Countries present in only one file are kept with the missing side counted
+    as zero. geos keeps only the listed countries. Results are sorted by
+    balance, biggest deficit first.
+    """
+    totals = _join_sector_totals(parser, year, key='geo', unit_filter=unit_filter)
+    balance = _keep_geos(totals['exports'].fillna(0) - totals['imports'].fillna(0), geos)
+    
+    return sorted(
+        [(geo, float(value)) for geo, value in balance.items()],
+        key=lambda item: item[1]
+    )
+
+def coverage_ratio_by_geo(
+    parser: FIGAROParser, year: int, unit_filter: Optional[str] = None
+) -> List[Tuple[str, float]]:
+    """Get each country's exports as a percentage of its imports over all NACE sectors.
+    
+    Countries without imports (zero or absent) are skipped since their
+    coverage is undefined; countries without exports cover 0%. Results are
+    sorted by coverage, highest first.
+    """
+    totals = _join_sector_totals(parser, year, key='geo', unit_filter=unit_filter)
+    totals = totals[totals['imports'].fillna(0) != 0]
+    coverage = (totals['exports'].fillna(0) / totals['imports'] * 100).sort_values(ascending=False, kind='stable')
+    
+    return [(geo, float(value)) for geo, value in coverage.items()]
+
+def compare_geos(
+    parser: FIGAROParser, flow_type: str, geo_a: str, geo_b: str, year: int, unit_filter: Optional[str] = None
+) -> List[Tuple[str, float, float]]:
+    """Get (nace, value_a, value_b) per NACE sector for two countries of one flow type.
+    
+    Sectors reported by only one country have NaN on the other side, and
+    sectors without a value for either are skipped. Results are sorted by
+    combined magnitude, largest first.
+    """
+    file_path = _flow_file(parser, flow_type)
+    df = parser.get_year_frame(file_path, year).dropna(subset=['value'])
+    df = parser.single_unit(df[df['geo'].isin([geo_a, geo_b])], os.path.basename(file_path), unit_filter)
+    totals = pd.concat(
+        [
+            df[df['geo'] == geo]
+            .groupby('nace_r2')['value']
+            .agg(exact_sum, min_count=1)
+            .astype(float)
+            .rename(geo_side)
+            for geo, geo_side in [(geo_a, 'a'), (geo_b, 'b')]
+        ],
+        axis=1
+    ).dropna(how='all')
+    magnitude = totals['a'].abs().fillna(0) + totals['b'].abs().fillna(0)
+    totals = totals.loc[magnitude.sort_values(ascending=False, kind='stable').index]
+    
+    return [(nace, float(row['a']), float(row['b'])) for nace, row in totals.iterrows()]
+
+def flows_by_partner_group(
+    parser: FIGAROParser, year: int, unit_filter: Optional[str] = None
+) -> List[Tuple[str, str, float]]:
+    """Get import and export totals split by whether the c_exp partner is in the EU.
+    
+    Partners are grouped as 'intra_eu', 'extra_eu' or, for codes that are not
+    a known country, 'other'. Groups without any non-NaN value are left out.
+    """
+    flows = []
+    for file_path, flow_type in _flow_files(parser):
+        df = _year_frame(parser, file_path, year, unit_filter)
+        totals = df.groupby(df['c_exp'].map(partner_group))['value'].agg(exact_sum)
+        flows.extend(
+            (group, flow_type, float(totals[group]))
+            for group in PARTNER_GROUPS
+            if group in totals.index
+        )
+    
+    return flows
+
+def _partner_totals(
+    parser: FIGAROParser, flow_type: str, nace: str, year: int, unit_filter: Optional[str] = None
+) -> pd.Series:
+    """Sum one sector's non-NaN values per c_exp partner, largest first."""
+    file_path = _flow_file(parser, flow_type)
+    df = parser.get_year_frame(file_path, year)
+    rows = df[df['nace_r2'] == nace]
+    if rows.empty:
+        raise ValueError(f"NACE {nace} not found in {os.path.basename(file_path)}")
+    
+    rows = parser.single_unit(rows.dropna(subset=['value']), os.path.basename(file_path), unit_filter)
+    totals = rows.groupby('c_exp')['value'].agg(exact_sum)
+    return totals.sort_values(ascending=False, kind='stable')
+
+def top_partners(
+    parser: FIGAROParser, flow_type: str, nace: str, year: int, top_n: int = 5, unit_filter: Optional[str] = None
+) -> List[Tuple[str, float]]:
+    """Get the top_n c_exp partners with the largest flows of one sector and flow type.
+    
+    Values are summed per partner over all countries, skipping NaN, and
+    sorted largest first. A sector without any row raises a ValueError.
+    """
+    totals = _partner_totals(parser, flow_type, nace, year, unit_filter).head(top_n)
+    return [(partner, float(value)) for partner, value in totals.items()]
+
+def partner_shares(
+    parser: FIGAROParser, flow_type: str, nace: str, year: int, unit_filter: Optional[str] = None
+) -> List[Tuple[str, float]]:
+    """Get every c_exp partner's percentage of one sector's flows, largest first.
+    
+    NaN values count towards neither a partner nor the total. A sector whose
+    total is zero has no meaningful shares and gives an empty list.
+    """
+    totals = _partner_totals(parser, flow_type, nace, year, unit_filter)
+    grand_total = exact_sum(totals)
+    if grand_total == 0:
+        return []
+    return [(partner, float(value / grand_total * 100)) for partner, value in totals.items()]
+
+def aggregate_geos(
+    parser: FIGAROParser,
+    year: int,
+    geo_group: List[str],
+    group_label: str,
+    flow_type: str = IMPORTS_FLOW,
+    unit_filter: Optional[str] = None
+) -> List[Tuple[str, str, float]]:
+    """Sum the values of a custom group of countries (e.g. Benelux) per NACE sector.
+    
+    Returns (nace, group_label, value) sorted by NACE code. Group members
+    absent from the file are ignored with a logged warning.
+    """
+    file_path = _flow_file(parser, flow_type)
+    df = parser.get_year_frame(file_path, year).dropna(subset=['value'])
+    
+    absent = sorted(set(geo_group) - set(df['geo']))
+    if absent:
+        logger.warning("Ignoring geo codes not found in %s data: %s", flow_type, ', '.join(absent))
+    
+    members = parser.single_unit(df[df['geo'].isin(geo_group)], os.path.basename(file_path), unit_filter)
+    totals = members.groupby('nace_r2')['value'].agg(exact_sum)
+    return [(nace, group_label, float(value)) for nace, value in totals.sort_index().items()]
+
+def growth_flows(
+    parser: FIGAROParser, year: int, prior_year: int, unit_filter: Optional[str] = None
+) -> List[Tuple[str, str, float]]:
+    """Get the percentage change between two years per NACE sector and flow type.
+    
+    Sectors missing either year, or with a zero prior value, are skipped since
+    their growth rate is undefined.
+    """
+    flows = []
+    for file_path, flow_type in _flow_files(parser):
+        totals = _yearly_totals(parser, file_path, [prior_year, year], unit_filter=unit_filter).dropna()
+        prior, current = totals[str(prior_year)], totals[str(year)]
+        
+        defined = prior != 0
+        growth = (current[defined] - prior[defined]) / prior[defined] * 100
+        flows.extend((nace, flow_type, float(value)) for nace, value in growth.items())
+    
+    return flows
+
+def diff_flows(
+    parser: FIGAROParser, year: int, prior_year: int, absolute: bool = False, unit_filter: Optional[str] = None
+) -> List[Tuple]:
+    """Get the absolute change between two years per NACE sector and flow type.
+    
+    Sectors missing either year are skipped rather than counted as zero.
+    Results are sorted by the magnitude of the change, largest first. With
+    absolute, changes are (nace, flow_type, magnitude, sign) as in net_flows.
+    """
+    flows = []
+    for file_path, flow_type in _flow_files(parser):
+        totals = _yearly_totals(parser, file_path, [prior_year, year], unit_filter=unit_filter).dropna()
+        change = totals[str(year)] - totals[str(prior_year)]
+        flows.extend((nace, flow_type, float(value)) for nace, value in change.items())
+    
+    flows = sorted(flows, key=lambda flow: abs(flow[2]), reverse=True)
+    if absolute:
+        return [(nace, flow_type, abs(value), _sign(value)) for nace, flow_type, value in flows]
+    return flows
+
+def cagr_flows(
+    parser: FIGAROParser, start_year: int, end_year: int, unit_filter: Optional[str] = None
+) -> List[Tuple[str, str, float]]:
+    """Get the compound annual growth rate in percent per NACE sector and flow type.
+    
+    CAGR is (end / start) ** (1 / (end_year - start_year)) - 1. It is undefined
+    for a zero, negative or missing start value and for a negative end value,
+    so those sectors are skipped.
+    """
+    if end_year <= start_year:
+        raise ValueError(f"end_year must be after start_year, got {start_year} to {end_year}")
+    
+    flows = []
+    for file_path, flow_type in _flow_files(parser):
+        totals = _yearly_totals(parser, file_path, [start_year, end_year], unit_filter=unit_filter).dropna()
+        start, end = totals[str(start_year)], totals[str(end_year)]
+        
+        defined = (start > 0) & (end >= 0)
+        cagr = ((end[defined] / start[defined]) ** (1 / (end_year - start_year)) - 1) * 100
+        flows.extend((nace, flow_type, float(value)) for nace, value in cagr.items())
+    
+    return flows
+
+def aggregate_nace_level(
+    parser: FIGAROParser, year: int, level: int, unit_filter: Optional[str] = None
+) -> List[Tuple[str, str, float]]:
+    """Roll NACE codes up to their first `level` characters and sum per flow type.
+    
+    Level 1 gives sections (e.g. 'C'), level 3 divisions (e.g. 'C10').
+    """
+    if level < 1:
+        raise ValueError(f"NACE level must be at least 1, got {level}")
+    
+    flows = []
+    for file_path, flow_type in _flow_files(parser):
+        df = _year_frame(parser, file_path, year, unit_filter).dropna(subset=['nace_r2'])
+        totals = df.groupby(df['nace_r2'].str[:level])['value'].agg(exact_sum)
+        flows.extend((nace, flow_type, float(value)) for nace, value in totals.items())
+    
+    return flows
+
+def aggregate_nace_growth(
+    parser: FIGAROParser,
+    year: int,
+    prior_year: int,
+    level: int,
+    volume_weighted: bool = False,
+    unit_filter: Optional[str] = None
+) -> List[Tuple[str, str, float]]:
+    """Roll sector growth rates up to the first `level` NACE characters per flow type.
+    
+    By default each group's growth is the plain average of its sectors' rates. With
+    `volume_weighted` each rate is weighted by its sector's `prior_year` value, so
+    tiny sectors no longer swing the aggregate. Sectors without a defined growth
+    rate are skipped, as in growth_flows.
+    """
+    if level < 1:
+        raise ValueError(f"NACE level must be at least 1, got {level}")
+    
+    flows = []
+    for file_path, flow_type in _flow_files(parser):
+        totals = _yearly_totals(parser, file_path, [prior_year, year], unit_filter=unit_filter).dropna()
+        totals = totals[totals[str(prior_year)] != 0]
+        prior, current = totals[str(prior_year)], totals[str(year)]
+        growth = (current - prior) / prior * 100
+        groups = growth.index.str[:level]
+        if volume_weighted:
+            weighted = (growth * prior).groupby(groups).agg(exact_sum)
+            aggregate = weighted / prior.groupby(groups).agg(exact_sum)
+        else:
+            aggregate = growth.groupby(groups).mean()
+        flows.extend((nace, flow_type, float(value)) for nace, value in aggregate.items())
+    
+    return flows
+
+def trade_ratio(parser: FIGAROParser, year: int, unit_filter: Optional[str] = None) -> List[Tuple[str, float]]:
+    """Get the exports-to-imports ratio per NACE sector, highest first.
+    
+    Sectors missing from either file or with zero imports are dropped rather
+    than reported as an infinite or undefined ratio.
+    """
+    totals = _join_sector_totals(parser, year, unit_filter=unit_filter).dropna()
+    totals = totals[totals['imports'] != 0]
+    ratio = totals['exports'] / totals['imports']
+    
+    return sorted(
+        [(nace, float(value)) for nace, value in ratio.items()],
+        key=lambda item: item[1],
+        reverse=True
+    )
+
+def flow_shares(
+    parser: FIGAROParser, year: int, other_threshold: Optional[float] = None, unit_filter: Optional[str] = None
+) -> List[Tuple[str, str, float]]:
+    """Get each NACE sector's percentage of the total imports or exports of a year.
+    
+    NaN values count towards neither a flow nor its total. A flow type whose
+    total is zero has no meaningful shares and is skipped.
+    
+    With other_threshold (a percentage), sectors below it are summed into a
+    single 'Other' entry listed last, e.g. to keep pie charts readable.
+    """
+    flows = []
+    for file_path, flow_type in _flow_files(parser):
+        totals = _totals_by(parser, file_path, year, unit_filter=unit_filter)
+        grand_total = exact_sum(totals)
+        if grand_total == 0:
+            continue
+        
+        shares = _bucket_other(totals / grand_total * 100, other_threshold)
+        flows.extend((nace, flow_type, float(value)) for nace, value in shares.items())
+    
+    return flows
+
+def concentration_index(parser: FIGAROParser, year: int, unit_filter: Optional[str] = None) -> Tuple[float, float]:
+    """Get the Herfindahl-Hirschman index of the imports and of the exports across NACE sectors.
+    
+    Each index is the sum of squared sector shares (as fractions) of the flow
+    type's total, from near 0 for trade spread over many sectors to 1 for a
+    single sector. NaN values are skipped, and a zero total gives NaN.
+    """
+    indices = []
+    for file_path, _ in _flow_files(parser):
+        totals = _totals_by(parser, file_path, year, unit_filter=unit_filter)
+        grand_total = exact_sum(totals)
+        indices.append(float(exact_sum((totals / grand_total) ** 2)) if grand_total != 0 else math.nan)
+    return indices[0], indices[1]
+
+def rank_sectors(
+    parser: FIGAROParser, year: int, flow_type: str, unit_filter: Optional[str] = None
+) -> List[Tuple[int, str, float]]:
+    """Get (rank, nace, value) for the NACE sectors of one flow type, rank 1 being the largest.
+    
+    Equal values share a rank and the next rank skips accordingly (1, 2, 2,
+    4), with ties listed by NACE code. NaN values are skipped before ranking.
+    """
+    totals = _totals_by(parser, _flow_file(parser, flow_type), year, unit_filter=unit_filter).astype(float).sort_index()
+    totals = totals.sort_values(ascending=False, kind='stable')
+    ranks = totals.rank(method='min', ascending=False)
+    return [(int(ranks[nace]), nace, float(value)) for nace, value in totals.items()]
+
+def sector_trade_share(
+    parser: FIGAROParser,
+    year: int,
+    top_n: Optional[int] = None,
+    other_threshold: Optional[float] = None,
+    unit_filter: Optional[str] = None
+) -> List[Tuple[str, float]]:
+    """Get each NACE sector's percentage of the combined imports and exports of a year.
+    
+    Sectors are sorted by share, largest first, and top_n keeps only the
+    largest. NaN values are skipped, and a year without trade gives no shares.
+    other_threshold merges small sectors into 'Other' as in flow_shares,
+    before top_n is applied.
+    """
+    totals = _join_sector_totals(parser, year, unit_filter=unit_filter).apply(exact_sum, axis=1)
+    grand_total = exact_sum(totals)
+    if grand_total == 0:
+        return []
+    
+    shares = (totals / grand_total * 100).sort_values(ascending=False, kind='stable')
+    shares = _bucket_other(shares, other_threshold)
+    if top_n is not None:
+        shares = shares.head(top_n)
+    return [(nace, float(value)) for nace, value in shares.items()]
+
+def deflate_flows(parser: FIGAROParser, year: int, deflator: float, **filters) -> List[Dict]:
+    """Get the flows of a year in real terms by dividing each value by a deflator.
+    
+    The deflator is the year's price index relative to a single base year
+    (e.g. 1.05 for prices 5% higher), so results are at base-year prices.
+    Keyword arguments are passed to get_flow_data as filters; a scale given
+    there still applies.
+    """
+    if not deflator > 0:
+        raise ValueError(f"deflator must be positive, got {deflator}")
+    return parser.get_flow_data(year, **{**filters, 'scale': filters.get('scale', 1.0) * deflator})
+
+def trade_openness(parser: FIGAROParser, year: int, denominator: float, unit_filter: Optional[str] = None) -> float:
+    """Get a year's total trade (imports + exports) over a denominator such as GDP.
+    
+    Values are summed over all sectors and countries, skipping NaN, and the
+    denominator must be in the unit of the flows.
+    """
+    if denominator == 0:
+        raise ValueError("denominator must not be zero")
+    totals = _join_sector_totals(parser, year, unit_filter=unit_filter)
+    total = exact_sum(pd.concat([totals['imports'], totals['exports']]))
+    return float(total) / denominator
+
+def flow_summary(parser: FIGAROParser, year: int) -> Dict[str, Dict[str, float]]:
+    """Get the distribution of a year's values per flow type, for choosing axis scales.
+    
+    Each flow type maps to its min, max, mean, median, p25 and p75 over all
+    series. NaN values are excluded; a flow type without values reports NaN.
+    """
+    summary = {}
+    for file_path, flow_type in _flow_files(parser):
+        values = parser.get_year_frame(file_path, year)['value'].dropna()
+        summary[flow_type] = {
+            'min': float(values.min()),
+            'max': float(values.max()),
+            'mean': float(values.mean()),
+            'median': float(values.median()),
+            'p25': float(values.quantile(0.25)),
+            'p75': float(values.quantile(0.75))
+        }
+    return summary
+
+def classify_sectors(
+    parser: FIGAROParser, year: int, tolerance: float = 0.0, unit_filter: Optional[str] = None
+) -> List[Tuple[str, str, float]]:
+    """Label each NACE sector as 'net_exporter', 'net_importer' or 'balanced'.
+    
+    Sectors whose absolute balance is within tolerance are 'balanced'; with the
+    default of zero only exactly balanced sectors fall in that bucket.
+    """
+    if tolerance < 0:
+        raise ValueError(f"tolerance must not be negative, got {tolerance}")
+    
+    classified = []
+    for nace, balance in net_flows(parser, year, unit_filter=unit_filter):
+        if abs(balance) <= tolerance:
+            label = 'balanced'
+        elif balance > 0:
+            label = 'net_exporter'
+        else:
+            label = 'net_importer'
+        classified.append((nace, label, balance))
+    
+    return classified
+
+def _column_totals(df: pd.DataFrame, columns: List[str]) -> pd.Series:
+    """Sum each of the columns over all rows, NaN for a column without any value."""
+    return pd.Series({col: exact_sum(df[col], min_count=1) for col in columns}, index=columns, dtype=float)
+
+def _sector_year_values(
+    parser: FIGAROParser, nace: str, geo: str, unit_filter: Optional[str] = None
+) -> List[Tuple[str, pd.Series]]:
+    """Get the yearly values of one sector-country pair per flow type.
+    
+    Each series is indexed by integer year in ascending order and keeps NaN
+    for years without a reported value. Each file is parsed once. Rows are
+    checked to share a single unit as in _year_frame, which every time
+    series function below relies on.
+    """
+    series = []
+    for file_path, flow_type in _flow_files(parser):
+        df = parser.read_frame(file_path)
+        rows = df[(df['nace_r2'] == nace) & (df['geo'] == geo)]
+        if rows.empty:
+            continue
+        rows = parser.single_unit(rows, os.path.basename(file_path), unit_filter)
+        
+        year_columns = [col for col in df.columns if col.isdigit()]
+        totals = _column_totals(rows, year_columns)
+        totals.index = totals.index.astype(int)
+        series.append((flow_type, totals.sort_index()))
+    
+    if not series:
+        raise ValueError(f"No series found for NACE {nace} and geo {geo}")
+    
+    return series
+
+def sector_time_series(
+    parser: FIGAROParser, nace: str, geo: str, unit_filter: Optional[str] = None
+) -> List[Tuple[int, str, float]]:
+    """Get the yearly imports and exports of one sector-country pair as (year, flow_type, value).
+    
+    Each file is parsed once. NaN years are skipped so a chart shows gaps
+    rather than spurious zeros.
+    """
+    series = [
+        (int(year), flow_type, float(value))
+        for flow_type, totals in _sector_year_values(parser, nace, geo, unit_filter)
+        for year, value in totals.items()
+        if not pd.isna(value)
+    ]
+    return sorted(series, key=lambda point: (point[0], point[1]))
+
+def balance_time_series(
+    parser: FIGAROParser, nace: str, geo: str, unit_filter: Optional[str] = None
+) -> List[Tuple[int, float]]:
+    """Get the yearly trade balance (exports - imports) of one sector-country pair as (year, balance).
+    
+    A side missing for a year counts as zero when the other side is
+    reported; years where both are missing are skipped.
+    """
+    totals = pd.DataFrame(dict(_sector_year_values(parser, nace, geo, unit_filter)))
+    totals = totals.reindex(columns=[IMPORTS_FLOW, EXPORTS_FLOW]).dropna(how='all').sort_index()
+    balance = totals[EXPORTS_FLOW].fillna(0) - totals[IMPORTS_FLOW].fillna(0)
+    return [(int(year), float(value)) for year, value in balance.items()]
+
+def cumulative_time_series(
+    parser: FIGAROParser, nace: str, geo: str, unit_filter: Optional[str] = None
+) -> List[Tuple[int, str, float]]:
+    """Get the running total of one sector-country pair's yearly flows as (year, flow_type, value).
+    
+    A NaN year carries the previous running total forward instead of
+    resetting it, so the last point is the sum of all reported years. Years
+    before the first reported value are skipped.
+    """
+    series = []
+    for flow_type, totals in _sector_year_values(parser, nace, geo, unit_filter):
+        running = totals.cumsum().ffill()
+        series.extend(
+            (int(year), flow_type, float(value))
+            for year, value in running.items()
+            if not pd.isna(value)
+        )
+    return sorted(series, key=lambda point: (point[0], point[1]))
+
+def indexed_time_series(
+    parser: FIGAROParser, nace: str, geo: str, base_year: int, unit_filter: Optional[str] = None
+) -> List[Tuple[int, str, float]]:
+    """Get one sector-country pair's yearly flows rebased to base_year = 100 as (year, flow_type, value).
+    
+    A base year missing from a file raises a MissingYearError, and a NaN or
+    zero base value a ValueError, since the whole index is then undefined.
+    NaN years are skipped as in sector_time_series.
+    """
+    series = []
+    for flow_type, totals in _sector_year_values(parser, nace, geo, unit_filter):
+        if base_year not in totals.index:
+            raise MissingYearError(f"Base year {base_year} not found for {flow_type}")
+        base = totals[base_year]
+        if pd.isna(base) or base == 0:
+            raise ValueError(f"{flow_type} of NACE {nace} and geo {geo} have no value in base year {base_year}")
+        
+        indexed = totals / base * 100
+        series.extend(
+            (int(year), flow_type, float(value))
+            for year, value in indexed.items()
+            if not pd.isna(value)
+        )
+    return sorted(series, key=lambda point: (point[0], point[1]))
+
+def sector_time_series_interpolated(
+    parser: FIGAROParser, nace: str, geo: str, unit_filter: Optional[str] = None
+) -> List[Tuple[int, str, float, bool]]:
+    """Get a sector time series with interior gaps linearly interpolated.
+    
+    Points are (year, flow_type, value, interpolated). Only NaN years
+    between two known values are filled; leading and trailing gaps are
+    still skipped, and a series with a single known year is returned as is.
+    """
+    series = []
+    for flow_type, totals in _sector_year_values(parser, nace, geo, unit_filter):
+        filled = totals.interpolate(method='index', limit_area='inside')
+        series.extend(
+            (int(year), flow_type, float(value), bool(pd.isna(totals[year])))
+            for year, value in filled.items()
+            if not pd.isna(value)
+        )
+    return sorted(series, key=lambda point: (point[0], point[1]))
+
+def import_export_correlation(
+    parser: FIGAROParser, nace: str, geo: str, unit_filter: Optional[str] = None
+) -> float:
+    """Get the Pearson correlation of one sector-country pair's yearly imports and exports.
+    
+    Only years with both values count. The result is NaN when fewer than
+    MIN_CORRELATION_POINTS such years exist, when the pair appears in only
+    one file, or when either series is constant.
+    """
+    series = dict(_sector_year_values(parser, nace, geo, unit_filter))
+    if IMPORTS_FLOW not in series or EXPORTS_FLOW not in series:
+        return float('nan')
+    
+    paired = pd.concat([series[IMPORTS_FLOW], series[EXPORTS_FLOW]], axis=1).dropna()
+    if len(paired) < MIN_CORRELATION_POINTS:
+        return float('nan')
+    return float(paired.iloc[:, 0].corr(paired.iloc[:, 1]))
+
+def smoothed_time_series(
+    parser: FIGAROParser, nace: str, geo: str, window: int, edges: str = 'shrink', unit_filter: Optional[str] = None
+) -> List[Tuple[int, str, float]]:
+    """Get a sector time series smoothed by a centered moving average over window years.
+    
+    window must be a positive odd integer. Near the first and last years
+    edges 'shrink' averages over the years available, while 'nan' reports
+    NaN there. Every year of the file is returned, with NaN where no value
+    can be averaged.
+    """
+    if isinstance(window, bool) or not isinstance(window, int) or window <= 0 or window % 2 == 0:
+        raise ValueError(f"window must be a positive odd integer, got {window}")
+    if edges not in SMOOTHING_EDGES:
+        raise ValueError(f"edges must be one of {', '.join(SMOOTHING_EDGES)}, got {edges}")
+    
+    series = []
+    for flow_type, totals in _sector_year_values(parser, nace, geo, unit_filter):
+        min_periods = 1 if edges == 'shrink' else window
+        smoothed = totals.rolling(window, center=True, min_periods=min_periods).mean()
+        series.extend((int(year), flow_type, float(value)) for year, value in smoothed.items())
+    return sorted(series, key=lambda point: (point[0], point[1]))
+
+def seasonal_decompose(
+    parser: FIGAROParser,
+    nace: str,
+    geo: str,
+    period: int,
+    flow_type: str = IMPORTS_FLOW,
+    unit_filter: Optional[str] = None
+) -> List[Tuple[str, float, float, float]]:
+    """Split a quarterly or monthly series into trend and seasonal parts.
+    
+    Points are (period_label, observed, trend, seasonal) in period order,
+    where period is the season length (4 for quarters, 12 for months). The
+    trend is a centered moving average over one season and the seasonal part
+    the mean detrended value of each position in the season, centered on
+    zero. Series shorter than two seasons only report the observed values,
+    with NaN trend and seasonal parts.
+    """
+    if isinstance(period, bool) or not isinstance(period, int) or period < 2:
+        raise ValueError(f"period must be an integer of at least 2, got {period}")
+    
+    file_path = _flow_file(parser, flow_type)
+    df = parser.read_frame(file_path)
+    rows = df[(df['nace_r2'] == nace) & (df['geo'] == geo)]
+    if rows.empty:
+        raise ValueError(f"No series found for NACE {nace} and geo {geo}")
+    rows = parser.single_unit(rows, os.path.basename(file_path), unit_filter)
+    
+    sub_annual = [
+        col for col in df.columns
+        if re.match(PERIOD_PATTERNS['Q'], col) or re.match(PERIOD_PATTERNS['M'], col)
+    ]
+    observed = _column_totals(rows, sorted(sub_annual)).reset_index(drop=True)
+    labels = sorted(sub_annual)
+    
+    if len(observed) < 2 * period:
+        trend = pd.Series(float('nan'), index=observed.index)
+        seasonal = trend
+    else:
+        if period % 2:
+            trend = observed.rolling(period, center=True).mean()
+        else:
+            # An even season needs a 2 x period average to stay centered
+            trend = observed.rolling(period).mean().rolling(2).mean().shift(-(period // 2))
+        
+        phase = observed.index % period
+        means = (observed - trend).groupby(phase).mean()
+        seasonal = (means - means.mean()).reindex(phase).reset_index(drop=True)
+    
+    return [
+        (label, float(observed[i]), float(trend[i]), float(seasonal[i]))
+        for i, label in enumerate(labels)
+    ]
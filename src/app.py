@@ -2,8 +2,16 @@ from flask import Flask, render_template, jsonify, request
 from src.parser import FIGAROParser
 import plotly.graph_objects as go
 import json
+import logging
 import os
 
+# Quiet by default; set FLOW_LOG_LEVEL=DEBUG to trace parsing and filtering
+log_level = os.environ.get('FLOW_LOG_LEVEL', 'WARNING').upper()
+known_level = log_level in logging.getLevelNamesMapping()
+logging.basicConfig(level=log_level if known_level else logging.WARNING)
+if not known_level:
+    logging.getLogger(__name__).warning("Unknown FLOW_LOG_LEVEL %r, using WARNING", log_level)
+
 app = Flask(__name__)
 data_dir = os.path.join(os.path.dirname(os.path.dirname(__file__)), 'data')
 parser = FIGAROParser(data_dir)
@@ -0,0 +1,30 @@
+# Error types raised while reading and processing flow data
+class FlowError(Exception):
+    """Base class of all flow data errors."""
+
+class FlowFileNotFoundError(FlowError, FileNotFoundError):
+    """An input file or output directory does not exist."""
+
+class MissingYearError(FlowError, ValueError):
+    """A requested year or period has no column in the data."""
+
+class EmptyYearError(FlowError, ValueError):
+    """A requested year has a column but no reported values."""
+
+class SchemaMismatchError(FlowError, ValueError):
+    """A file does not have the expected metadata fields or columns."""
+
+class FlowParseError(FlowError, ValueError):
+    """A file could not be parsed as delimited flow data."""
+
+class DuplicateSeriesError(FlowError, ValueError):
+    """The same series appears more than once in a file."""
+
+class FlowDownloadError(FlowError, OSError):
+    """A remote flow file could not be downloaded."""
+
+class NegativeValueError(FlowError, ValueError):
+    """Trade values that should not be negative are."""
+
+class MixedUnitsError(FlowError, ValueError):
+    """Values in different units would be summed together."""
@@ -0,0 +1,80 @@
+# Eurostat geo codes and their English names
+#
+# Mostly ISO 3166-1 alpha-2, except that Eurostat uses EL for Greece and UK for
+# the United Kingdom, and adds aggregates such as EU27_2020 and the FIGARO rest
+# of the world (FIGW1).
+GEO_NAMES = {
+    'AT': 'Austria',
+    'BE': 'Belgium',
+    'BG': 'Bulgaria',
+    'CY': 'Cyprus',
+    'CZ': 'Czechia',
+    'DE': 'Germany',
+    'DK': 'Denmark',
+    'EE': 'Estonia',
+    'EL': 'Greece',
+    'ES': 'Spain',
+    'FI': 'Finland',
+    'FR': 'France',
+    'HR': 'Croatia',
+    'HU': 'Hungary',
+    'IE': 'Ireland',
+    'IT': 'Italy',
+    'LT': 'Lithuania',
+    'LU': 'Luxembourg',
+    'LV': 'Latvia',
+    'MT': 'Malta',
+    'NL': 'Netherlands',
+    'PL': 'Poland',
+    'PT': 'Portugal',
+    'RO': 'Romania',
+    'SE': 'Sweden',
+    'SI': 'Slovenia',
+    'SK': 'Slovakia',
+    'CH': 'Switzerland',
+    'IS': 'Iceland',
+    'LI': 'Liechtenstein',
+    'NO': 'Norway',
+    'UK': 'United Kingdom',
+    'AR': 'Argentina',
+    'AU': 'Australia',
+    'BR': 'Brazil',
+    'CA': 'Canada',
+    'CN': 'China',
+    'ID': 'Indonesia',
+    'IN': 'India',
+    'JP': 'Japan',
+    'KR': 'South Korea',
+    'MX': 'Mexico',
+    'RU': 'Russia',
+    'SA': 'Saudi Arabia',
+    'TR': 'Türkiye',
+    'US': 'United States',
+    'ZA': 'South Africa',
+    'EU27_2020': 'European Union (27 countries)',
+    'FIGW1': 'Rest of the world',
+}
+
+
+# Member states of the EU27_2020 aggregate
+EU_MEMBERS = frozenset([
+    'AT', 'BE', 'BG', 'CY', 'CZ', 'DE', 'DK', 'EE', 'EL', 'ES', 'FI', 'FR', 'HR', 'HU',
+    'IE', 'IT', 'LT', 'LU', 'LV', 'MT', 'NL', 'PL', 'PT', 'RO', 'SE', 'SI', 'SK',
+])
+
+# Partner groups of partner_group, in reporting order
+PARTNER_GROUPS = ('intra_eu', 'extra_eu', 'other')
+
+
+def geo_name(code: str) -> str:
+    """Get the English name of a geo code, or the code itself if it is unknown."""
+    return GEO_NAMES.get(code, code)
+
+
+def partner_group(code: str) -> str:
+    """Classify a partner code as 'intra_eu', 'extra_eu' or, when it is not a known country, 'other'."""
+    if code in EU_MEMBERS:
+        return 'intra_eu'
+    if code in GEO_NAMES and code != 'EU27_2020':
+        return 'extra_eu'
+    return 'other'
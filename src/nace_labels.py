@@ -0,0 +1,92 @@
+# NACE Rev. 2 codes and their English descriptions
+#
+# Covers the sections and the industry groupings used by FIGARO, which joins
+# some divisions into ranges (C10-C12) or pairs (C31_C32).
+NACE_LABELS = {
+    'TOTAL': 'Total - all NACE activities',
+    'A': 'Agriculture, forestry and fishing',
+    'A01': 'Crop and animal production, hunting and related service activities',
+    'A02': 'Forestry and logging',
+    'A03': 'Fishing and aquaculture',
+    'B': 'Mining and quarrying',
+    'C': 'Manufacturing',
+    'C10-C12': 'Manufacture of food products, beverages and tobacco products',
+    'C13-C15': 'Manufacture of textiles, wearing apparel, leather and related products',
+    'C16': 'Manufacture of wood and of products of wood and cork, except furniture',
+    'C17': 'Manufacture of paper and paper products',
+    'C18': 'Printing and reproduction of recorded media',
+    'C19': 'Manufacture of coke and refined petroleum products',
+    'C20': 'Manufacture of chemicals and chemical products',
+    'C21': 'Manufacture of basic pharmaceutical products and pharmaceutical preparations',
+    'C22': 'Manufacture of rubber and plastic products',
+    'C23': 'Manufacture of other non-metallic mineral products',
+    'C24': 'Manufacture of basic metals',
+    'C25': 'Manufacture of fabricated metal products, except machinery and equipment',
+    'C26': 'Manufacture of computer, electronic and optical products',
+    'C27': 'Manufacture of electrical equipment',
+    'C28': 'Manufacture of machinery and equipment n.e.c.',
+    'C29': 'Manufacture of motor vehicles, trailers and semi-trailers',
+    'C30': 'Manufacture of other transport equipment',
+    'C31_C32': 'Manufacture of furniture; other manufacturing',
+    'C33': 'Repair and installation of machinery and equipment',
+    'D': 'Electricity, gas, steam and air conditioning supply',
+    'D35': 'Electricity, gas, steam and air conditioning supply',
+    'E': 'Water supply; sewerage, waste management and remediation activities',
+    'E36': 'Water collection, treatment and supply',
+    'E37-E39': 'Sewerage, waste management and remediation activities',
+    'F': 'Construction',
+    'G': 'Wholesale and retail trade; repair of motor vehicles and motorcycles',
+    'G45': 'Wholesale and retail trade and repair of motor vehicles and motorcycles',
+    'G46': 'Wholesale trade, except of motor vehicles and motorcycles',
+    'G47': 'Retail trade, except of motor vehicles and motorcycles',
+    'H': 'Transportation and storage',
+    'H49': 'Land transport and transport via pipelines',
+    'H50': 'Water transport',
+    'H51': 'Air transport',
+    'H52': 'Warehousing and support activities for transportation',
+    'H53': 'Postal and courier activities',
+    'I': 'Accommodation and food service activities',
+    'J': 'Information and communication',
+    'J58': 'Publishing activities',
+    'J59_J60': 'Motion picture, video and television programme production; programming and broadcasting',
+    'J61': 'Telecommunications',
+    'J62_J63': 'Computer programming, consultancy and information service activities',
+    'K': 'Financial and insurance activities',
+    'K64': 'Financial service activities, except insurance and pension funding',
+    'K65': 'Insurance, reinsurance and pension funding, except compulsory social security',
+    'K66': 'Activities auxiliary to financial services and insurance activities',
+    'L': 'Real estate activities',
+    'L68': 'Real estate activities',
+    'M': 'Professional, scientific and technical activities',
+    'M69_M70': 'Legal and accounting activities; activities of head offices; management consultancy',
+    'M71': 'Architectural and engineering activities; technical testing and analysis',
+    'M72': 'Scientific research and development',
+    'M73': 'Advertising and market research',
+    'M74_M75': 'Other professional, scientific and technical activities; veterinary activities',
+    'N': 'Administrative and support service activities',
+    'N77': 'Rental and leasing activities',
+    'N78': 'Employment activities',
+    'N79': 'Travel agency, tour operator and other reservation service and related activities',
+    'N80-N82': 'Security and investigation, service to buildings and landscape, office administrative and support activities',
+    'O': 'Public administration and defence; compulsory social security',
+    'O84': 'Public administration and defence; compulsory social security',
+    'P': 'Education',
+    'P85': 'Education',
+    'Q': 'Human health and social work activities',
+    'Q86': 'Human health activities',
+    'Q87_Q88': 'Residential care activities and social work activities without accommodation',
+    'R': 'Arts, entertainment and recreation',
+    'R90-R92': 'Creative, arts and entertainment activities; libraries, archives, museums; gambling and betting',
+    'R93': 'Sports activities and amusement and recreation activities',
+    'S': 'Other service activities',
+    'S94': 'Activities of membership organisations',
+    'S95': 'Repair of computers and personal and household goods',
+    'S96': 'Other personal service activities',
+    'T': 'Activities of households as employers; undifferentiated goods- and services-producing activities of households for own use',
+    'U': 'Activities of extraterritorial organisations and bodies',
+}
+
+
+def nace_label(code: str) -> str:
+    """Get the description of a NACE code, or the code itself if it is unknown."""
+    return NACE_LABELS.get(code, code)
@@ -1,37 +1,811 @@
-from typing import List, Dict, Union, Optional
+from typing import Callable, Iterator, List, Dict, Union, Optional, Tuple
+from collections import OrderedDict
+from concurrent.futures import ThreadPoolExecutor
+import asyncio
+from dataclasses import asdict, dataclass, fields
+from itertools import islice
+import codecs
+import glob
+import importlib.util
+import gzip
+import io
+import logging
+import math
 import os
+import re
+import threading
+import urllib.error
+import urllib.request
+import zipfile
+import numpy as np
 import pandas as pd
+from src.errors import (
+    FlowFileNotFoundError, MissingYearError, SchemaMismatchError, FlowParseError, DuplicateSeriesError,
+    FlowDownloadError, NegativeValueError, EmptyYearError, MixedUnitsError
+)
+from src import __version__
+from src.nace_labels import nace_label
+
+# Eurostat marks missing observations with ':' and appends flag letters to values
+# (p = provisional, e = estimated, b = break in series, c = confidential, ...)
+MISSING_MARKER = ':'
+VALUE_PATTERN = r'^\s*(?P<number>.*?)\s*(?P<flags>[pebcdusrnf]*)\s*$'
+INTEGER_PATTERN = r'[+-]?\d+'
+
+GZIP_MAGIC = b'\x1f\x8b'
+
+# Time axis label Eurostat appends to the last metadata header, as in 'geo\time'
+TIME_AXIS_PATTERN = r'\\(?:time|TIME_PERIOD)$'
+
+logger = logging.getLogger(__name__)
+
+# Extensions of delimited text inputs; anything else must be Parquet or Arrow
+TEXT_EXTENSIONS = ('.tsv', '.csv', '.txt')
+ARROW_EXTENSIONS = ('.arrow', '.feather')
+
+# Input formats read into memory as whole columns rather than parsed as text
+COLUMNAR_FORMATS = ('parquet', 'arrow')
+
+# Column separators tried when sniffing a file, in order of preference. Comma
+# comes last because FIGARO packs comma-separated metadata into the first column.
+SEPARATOR_CANDIDATES = ['\t', ';', ',']
+SNIFF_LINES = 5
+
+# Rows parsed at a time when streaming a file instead of reading it whole
+STREAM_CHUNK_ROWS = 100_000
+
+# Column label formats of annual, quarterly and monthly observations, keyed by freq
+PERIOD_PATTERNS = {
+    'A': r'^\d{4}$',
+    'Q': r'^\d{4}Q[1-4]$',
+    'M': r'^\d{4}M(0[1-9]|1[0-2])$'
+}
+
+# Fields identifying a series, and the ways to resolve series repeated in a file
+SERIES_KEY = ['geo', 'nace_r2', 'unit']
+DUPLICATE_POLICIES = ('sum', 'first', 'error')
+
+# Ways to resolve a period column header repeated within a file
+DUPLICATE_YEAR_POLICIES = ('sum', 'first', 'last', 'error')
+
+# Ways to return missing values: drop them, report them as 0.0, or keep them as None
+NAN_MODES = ('skip', 'zero', 'keep')
+
+# Flow fields get_flow_data can sort by, keyed by sort_by name
+SORT_KEYS = {'value': 'value', 'nace': 'source', 'flow_type': 'target'}
+
+# Input layouts: Eurostat's one column per period, or tidy one row per series and year
+LAYOUTS = ('wide', 'long')
+LONG_LAYOUT_COLUMNS = ['nace_r2', 'geo', 'year', 'value']
+
+# Flow type labels used as Sankey targets
+IMPORTS_FLOW = "Total Imports"
+EXPORTS_FLOW = "Total Exports"
+
+# Fields packed into the comma-separated first column of FIGARO files
+METADATA_COLUMNS = ['freq', 'nace_r2', 'c_exp', 'unit', 'geo']
+
+# A flow file given either as a path or as its raw (possibly gzipped) bytes
+Source = Union[str, bytes]
+
+# File formats write_flows can produce, by extension
+OUTPUT_FORMATS = ('csv', 'json', 'parquet')
+
+# Called with (completed, total) as multi-year or multi-file extraction advances
+ProgressCallback = Callable[[int, int], None]
+
+# Most parsed frames kept at once; each year or column subset of a file is its own entry
+FRAME_CACHE_SIZE = 32
+
+# Parsed frames keyed by (path, mtime, read options), shared by all parsers, least recently used first
+_FRAME_CACHE: 'OrderedDict[tuple, pd.DataFrame]' = OrderedDict()
+_FRAME_CACHE_LOCK = threading.Lock()
+
+def clear_tsv_cache() -> None:
+    """Drop all cached parsed frames to release their memory."""
+    with _FRAME_CACHE_LOCK:
+        _FRAME_CACHE.clear()
+
+def version_info() -> Dict[str, object]:
+    """Get the package and pandas versions and which optional input and output formats are available.
+    
+    Parquet and Arrow support depend on pyarrow being installed; gzip, ZIP,
+    HTTP and streaming reads only use the standard library and pandas.
+    """
+    pyarrow = importlib.util.find_spec('pyarrow') is not None
+    return {
+        'version': __version__,
+        'pandas': pd.__version__,
+        'features': {
+            'gzip': True,
+            'zip': True,
+            'http': True,
+            'streaming': True,
+            'parquet': pyarrow,
+            'arrow': pyarrow
+        }
+    }
+
+# Downloaded file contents keyed by URL, shared by all parsers
+_DOWNLOAD_CACHE: Dict[str, bytes] = {}
+_DOWNLOAD_CACHE_LOCK = threading.Lock()
+DOWNLOAD_TIMEOUT = 60
+
+def clear_download_cache() -> None:
+    """Drop all cached downloads so the next request fetches them again."""
+    with _DOWNLOAD_CACHE_LOCK:
+        _DOWNLOAD_CACHE.clear()
+
+# Thread budget set by configure_threadpool; None keeps one thread per flow file
+_THREADPOOL_SIZE: Optional[int] = None
+
+def configure_threadpool(n: int) -> None:
+    """Cap the threads used for parsing, e.g. when embedding in a multi-threaded server.
+    
+    Sets the default max_threads of get_flow_data and, when installed,
+    pyarrow's global CPU pool used for Parquet and Arrow reads. Call it once
+    at startup, before the first parse; pools already running keep their size.
+    """
+    global _THREADPOOL_SIZE
+    if n < 1:
+        raise ValueError(f"Thread pool size must be at least 1, got {n}")
+    if importlib.util.find_spec('pyarrow') is not None:
+        import pyarrow as pa
+        pa.set_cpu_count(n)
+    _THREADPOOL_SIZE = n
+
+def exact_sum(values: pd.Series, min_count: int = 0):
+    """Sum the non-NaN values of a series without accumulated rounding error.
+    
+    Integers (e.g. from Int64 columns) are added as Python ints, so totals
+    beyond 2**53 stay exact; floats use math.fsum, whose result is the exact
+    sum rounded once. Fewer than min_count values give NaN, as in Series.sum.
+    
+    It runs per group in Python, so it is meant for the analysis totals; the
+    parser's own grouping of bulk files keeps pandas' vectorized sums.
+    """
+    items = values.dropna().tolist()
+    if len(items) < min_count:
+        return math.nan
+    if all(isinstance(item, (int, np.integer)) for item in items):
+        return sum(int(item) for item in items)
+    return math.fsum(items)
+
+@dataclass(frozen=True)
+class Flow:
+    """A single trade flow, the typed counterpart of the flow dicts."""
+    nace: str
+    flow_type: str
+    value: Optional[Union[int, float]]  # Unscaled whole numbers stay exact ints, as in the flow dicts
+    unit: Optional[str] = None
+
+@dataclass
+class FlowOptions:
+    """The filters of get_flow_data, with the same defaults, kept together in one value."""
+    min_value: Optional[float] = 0.0
+    drop_nan: bool = True
+    nace_level: Optional[int] = None
+    regions: Optional[List[str]] = None
+    include_imports: bool = True
+    include_exports: bool = True
+    geo: Optional[str] = None
+    top_n: Optional[int] = None
+    nace_prefix: Optional[str] = None
+    on_duplicate: str = 'error'
+    nan_mode: str = 'skip'
+    scale: float = 1.0
+    streaming: bool = False
+    round_decimals: Optional[int] = None
+    strict: bool = True
+    reject_negative: bool = False
+    max_threads: Optional[int] = None
+    with_geo: bool = False
+    with_nace_labels: bool = False
+    freq_filter: Optional[str] = None
+    import_label: str = IMPORTS_FLOW
+    export_label: str = EXPORTS_FLOW
+    sort_by: Optional[str] = None
+    descending: bool = True
+    error_on_empty_year: bool = False
+    include_nace: Optional[List[str]] = None
+    exclude_nace: Optional[List[str]] = None
+
+    @classmethod
+    def from_dict(cls, options: Dict) -> 'FlowOptions':
+        """Build options from a dict such as request parameters, rejecting unknown keys."""
+        known = {field.name for field in fields(cls)}
+        unknown = sorted(set(options) - known)
+        if unknown:
+            raise ValueError(f"Unknown flow options: {', '.join(unknown)}; expected some of {', '.join(sorted(known))}")
+        return cls(**options)
 
 class FIGAROParser:
     """Parser for FIGARO format TSV files."""
     
-    def __init__(self, data_dir: str):
-        """Initialize the FIGARO parser with data directory path."""
+    def __init__(
+        self, 
+        data_dir: str, 
+        metadata_columns: Optional[List[str]] = None,
+        separator: Optional[str] = None,
+        encoding: str = 'utf-8',
+        decimal_comma: bool = False,
+        zip_entry: Optional[str] = None,
+        skip_bad_rows: bool = False,
+        on_duplicate_year: str = 'error',
+        memory_map: bool = False,
+        treat_zero_as_missing: bool = False,
+        strict_metadata: bool = False,
+        column_map: Optional[Dict[str, str]] = None,
+        thousands_separator: Optional[str] = None,
+        layout: str = 'wide',
+        skip_rows_after_header: int = 0,
+        null_values: Optional[List[str]] = None
+    ):
+        """Initialize the FIGARO parser with data directory path.
+        
+        metadata_columns names the comma-separated fields of the first column
+        and defaults to the FIGARO layout (freq, nace_r2, c_exp, unit, geo).
+        The column separator is sniffed from each file unless given explicitly
+        as a single character (e.g. ';' or '|').
+        encoding (e.g. 'latin-1' for older exports) applies to all files, and
+        a leading UTF-8 byte order mark is dropped whatever the encoding.
+        decimal_comma reads values such as '1 234,5' (space thousands, comma
+        decimals) as 1234.5; the comma-separated metadata is unaffected.
+        thousands_separator (e.g. ',' for '1,234,567' or ' ' for '1 234 567')
+        is removed from values before they are converted. It cannot be the
+        decimal mark: ',' conflicts with decimal_comma, and '.' needs it.
+        Files may also be ZIP archives holding a single TSV, or several with
+        zip_entry naming the one to read.
+        
+        A row with more or fewer fields than the header fails the parse or
+        is padded with NaN, respectively. With skip_bad_rows such rows are
+        left out instead and counted in the quality report.
+        
+        A period header repeated in a file (e.g. two '2020' columns) raises a
+        SchemaMismatchError unless on_duplicate_year is 'sum', 'first' or
+        'last'.
+        
+        memory_map reads plain local text files through a memory map, which
+        can speed up the first read of large files. Compressed, zipped and
+        in-memory sources are read normally.
+        
+        Explicit zeros are kept apart from the ':' missing marker. For datasets
+        where 0 means "not reported", treat_zero_as_missing reads zeros as
+        missing values, so nan_mode handles them like ':'.
+        
+        Rows whose packed metadata has more or fewer comma-separated fields
+        than metadata_columns get NaN for the fields they lack and are counted
+        in the quality report. With strict_metadata they raise a
+        SchemaMismatchError listing their row indices instead.
+        
+        column_map maps metadata field names to the headers a nonstandard
+        file uses for them, e.g. {'nace_r2': 'sector', 'geo': 'country'}.
+        Such headers are renamed on reading, and files with one column per
+        field then find each field by name whatever the column order.
+        
+        layout='long' reads tidy files with one row per series and year and
+        at least nace_r2, geo, year and value columns (e.g. a pre-melted
+        CSV); other metadata fields are optional and left empty. A long file
+        lacking one of these columns raises a SchemaMismatchError.
+        
+        skip_rows_after_header drops that many rows right below the header,
+        such as a units or description row, before anything is parsed.
+        
+        null_values lists the tokens read as missing values besides empty
+        cells, [':'] by default as in Eurostat files; e.g. ['N/A', '-'] for
+        other sources. Flags after a token are kept, as for ': c'.
+        """
+        if separator is not None and len(separator) != 1:
+            raise ValueError(f"separator must be a single character, got {separator!r}")
+        if thousands_separator is not None:
+            if len(thousands_separator) != 1:
+                raise ValueError(f"thousands_separator must be a single character, got {thousands_separator!r}")
+            if thousands_separator == (',' if decimal_comma else '.'):
+                raise ValueError(f"thousands_separator {thousands_separator!r} is also the decimal mark")
+        if null_values is not None and not all(token.strip() for token in null_values):
+            raise ValueError("null_values must not contain empty tokens; empty cells are always missing")
+        if skip_rows_after_header < 0:
+            raise ValueError(f"skip_rows_after_header must not be negative, got {skip_rows_after_header}")
+        if layout not in LAYOUTS:
+            raise ValueError(f"layout must be one of {', '.join(LAYOUTS)}, got {layout}")
+        if on_duplicate_year not in DUPLICATE_YEAR_POLICIES:
+            raise ValueError(
+                f"on_duplicate_year must be one of {', '.join(DUPLICATE_YEAR_POLICIES)}, got {on_duplicate_year}"
+            )
+        
         self.data_dir = data_dir
-        self.imports_file = os.path.join(data_dir, 'figaro', 'estat_naio_10_fgti.tsv')
-        self.exports_file = os.path.join(data_dir, 'figaro', 'estat_naio_10_fgte.tsv')
+        self.metadata_columns = list(metadata_columns or METADATA_COLUMNS)
+        self.column_map = dict(column_map or {})
+        unknown = sorted(set(self.column_map) - set(self.metadata_columns))
+        if unknown:
+            raise ValueError(f"Unknown fields in column_map: {', '.join(unknown)}; expected some of {', '.join(self.metadata_columns)}")
+        self.separator = separator
+        self.encoding = encoding
+        self.decimal_comma = decimal_comma
+        self.thousands_separator = thousands_separator
+        self.zip_entry = zip_entry
+        self.skip_bad_rows = skip_bad_rows
+        self.on_duplicate_year = on_duplicate_year
+        self.memory_map = memory_map
+        self.treat_zero_as_missing = treat_zero_as_missing
+        self.strict_metadata = strict_metadata
+        self.layout = layout
+        self.skip_rows_after_header = skip_rows_after_header
+        self.null_values = (
+            tuple(token.strip() for token in null_values) if null_values is not None else (MISSING_MARKER,)
+        )
+        self.imports_file = self._resolve_data_file(os.path.join(data_dir, 'figaro', 'estat_naio_10_fgti.tsv'))
+        self.exports_file = self._resolve_data_file(os.path.join(data_dir, 'figaro', 'estat_naio_10_fgte.tsv'))
         
         # Check if required files exist
         for file_path in [self.imports_file, self.exports_file]:
             if not os.path.exists(file_path):
-                raise FileNotFoundError(f"Required FIGARO file not found: {file_path}")
+                raise FlowFileNotFoundError(f"Required FIGARO file not found: {file_path}")
+
+    def _resolve_data_file(self, file_path: str) -> str:
+        """Fall back to the gzipped bulk download, a Parquet or Arrow copy or a ZIP archive when the plain TSV is absent."""
+        stem = os.path.splitext(file_path)[0]
+        candidates = [file_path, file_path + '.gz', stem + '.parquet', stem + '.arrow', stem + '.feather', stem + '.zip']
+        return next((candidate for candidate in candidates if os.path.exists(candidate)), file_path)
+
+    def _source_name(self, source: Source) -> str:
+        """Get a short name of a source for error messages."""
+        return '<bytes>' if isinstance(source, bytes) else os.path.basename(source)
+
+    def _open_binary(self, source: Source) -> io.BufferedIOBase:
+        """Open a source as a binary stream."""
+        return io.BytesIO(source) if isinstance(source, bytes) else open(source, 'rb')
+
+    def _detect_compression(self, source: Source) -> Optional[str]:
+        """Detect gzip input from its magic number regardless of the extension."""
+        with self._open_binary(source) as f:
+            if f.read(2) == GZIP_MAGIC:
+                return 'gzip'
+        return None
+
+    def _detect_separator(self, source: Source) -> str:
+        """Pick the first candidate separator with a consistent field count."""
+        with self._open_binary(source) as raw:
+            stream = gzip.GzipFile(fileobj=raw) if self._detect_compression(source) == 'gzip' else raw
+            lines = [
+                line.decode('utf-8', errors='replace').rstrip('\r\n')
+                for line in islice(stream, SNIFF_LINES + self.skip_rows_after_header)
+            ]
+        lines = [line for line in lines[:1] + lines[1 + self.skip_rows_after_header:] if line]
+        
+        for candidate in SEPARATOR_CANDIDATES:
+            counts = [line.count(candidate) for line in lines]
+            if counts and counts[0] > 0 and all(count == counts[0] for count in counts):
+                return candidate
+        
+        # No consistent candidate, fall back to the most frequent one in the header
+        header = lines[0] if lines else ''
+        return max(SEPARATOR_CANDIDATES, key=header.count)
+
+    def _is_period_column(self, col: str) -> bool:
+        """Check whether a column holds values of a year, quarter or month."""
+        return any(re.match(pattern, col) for pattern in PERIOD_PATTERNS.values())
+
+    def _flow_files(self, include_imports: bool = True, include_exports: bool = True) -> List[Tuple[str, str]]:
+        """Get the (file path, flow type) pairs to process."""
+        flow_files = []
+        if include_imports:
+            flow_files.append((self.imports_file, IMPORTS_FLOW))
+        if include_exports:
+            flow_files.append((self.exports_file, EXPORTS_FLOW))
+        return flow_files
+
+    def _clean_value_column(self, column: pd.Series) -> Tuple[pd.Series, pd.Series]:
+        """Split a raw year column into numeric values and Eurostat value flags.
+        
+        A column of whole numbers without flags becomes a nullable Int64
+        column so large values are kept exactly; any fractional or flagged
+        value makes it float64.
+        
+        Numbers may be plain or in scientific notation ('1230000', '1.23E6',
+        '1.23e+06'), optionally followed by flags ('1.23E6 p'). A lowercase
+        exponent is not mistaken for the 'e' (estimated) flag since flags only
+        follow the whole number.
+        """
+        if column.dtype != 'object':
+            return pd.to_numeric(column, errors='coerce'), pd.Series(None, index=column.index, dtype='object')
+        
+        parts = column.str.extract(VALUE_PATTERN)
+        numbers = parts['number'].mask(parts['number'].isin(self.null_values))
+        if self.thousands_separator == ' ':
+            numbers = numbers.str.replace(r'\s', '', regex=True)  # Also non-breaking and thin spaces
+        elif self.thousands_separator is not None:
+            numbers = numbers.str.replace(self.thousands_separator, '', regex=False)
+        if self.decimal_comma:
+            numbers = numbers.str.replace(r'\s', '', regex=True).str.replace(',', '.', regex=False)
+        flags = parts['flags'].mask(parts['flags'] == '')
+        
+        # Whole numbers stay exact as Int64, even beyond the 2**53 float precision
+        present = numbers.dropna()
+        if len(present) and flags.isna().all() and present.str.fullmatch(INTEGER_PATTERN).all():
+            try:
+                values = pd.array([int(n) if isinstance(n, str) else pd.NA for n in numbers], dtype='Int64')
+                return pd.Series(values, index=column.index), flags
+            except (OverflowError, TypeError):
+                pass  # Beyond Int64, fall back to floats
+        return pd.to_numeric(numbers, errors='coerce'), flags
+
+    def _read_and_clean_df(
+        self, 
+        file_path: Source, 
+        nrows: Optional[int] = None,
+        with_flags: bool = False,
+        columns: Optional[List[str]] = None
+    ) -> pd.DataFrame:
+        """Read and clean a dataframe from a TSV file.
+        
+        When with_flags is set, a '<year>_flag' column holding the stripped
+        Eurostat flags (or None) is added next to each year column. If columns
+        lists some value columns (e.g. ['2019']), only those and the metadata
+        are read. Parsed frames are cached per file path and modification
+        time, up to FRAME_CACHE_SIZE frames with the least recently used
+        dropped first, and callers always receive their own copy. Raw bytes
+        are parsed without caching.
+        """
+        if isinstance(file_path, bytes):
+            return self._parse_df(file_path, nrows, with_flags, columns)
+        
+        path = os.path.abspath(file_path)
+        key = (
+            path, os.stat(path).st_mtime_ns, nrows, with_flags, tuple(columns) if columns else None
+        ) + self._read_options()
+        
+        with _FRAME_CACHE_LOCK:
+            cached = _FRAME_CACHE.get(key)
+            if cached is not None:
+                _FRAME_CACHE.move_to_end(key)
+        if cached is not None:
+            return cached.copy()
+        
+        df = self._parse_df(file_path, nrows, with_flags, columns)
+        
+        with _FRAME_CACHE_LOCK:
+            # Drop frames parsed from an older version of the same file
+            for stale_key in [k for k in _FRAME_CACHE if k[0] == path and k[1] != key[1]]:
+                del _FRAME_CACHE[stale_key]
+            _FRAME_CACHE[key] = df
+            while len(_FRAME_CACHE) > FRAME_CACHE_SIZE:
+                _FRAME_CACHE.popitem(last=False)
+        return df.copy()
 
-    def _read_and_clean_df(self, file_path: str, nrows: Optional[int] = None) -> pd.DataFrame:
-        """Read and clean a dataframe from a TSV file."""
-        df = pd.read_csv(file_path, sep='\t', engine='python', nrows=nrows)
+    def _read_options(self) -> tuple:
+        """Get the parser settings that change how a file is read, for cache keys."""
+        return (self.separator, self.encoding, self.decimal_comma, self.thousands_separator,
+                tuple(self.metadata_columns), self.zip_entry,
+                self.skip_bad_rows, self.on_duplicate_year, self.treat_zero_as_missing, self.strict_metadata,
+                tuple(sorted(self.column_map.items())), self.layout,
+                self.skip_rows_after_header, self.null_values)
+
+    def _parse_df(
+        self, 
+        file_path: Source, 
+        nrows: Optional[int], 
+        with_flags: bool, 
+        columns: Optional[List[str]] = None
+    ) -> pd.DataFrame:
+        """Parse and clean a TSV file without consulting the cache."""
+        df = self._clean_cells(self._read_raw_df(file_path, nrows, columns), with_flags)
         
-        # Clean whitespace from column names
-        df.columns = df.columns.str.strip()
+        if logger.isEnabledFor(logging.DEBUG):
+            period_columns = [col for col in df.columns if self._is_period_column(col)]
+            packed = self._packed_column(df)
+            metadata_fields = len(self._metadata_source_columns(df)) if packed is None else len(str(packed).split(','))
+            logger.debug(
+                "Read %d rows from %s: %d period columns, %d metadata fields",
+                len(df), self._source_name(file_path), len(period_columns), metadata_fields
+            )
+            
+        return self._categorize_metadata(df)
+
+    def _categorize_metadata(self, df: pd.DataFrame) -> pd.DataFrame:
+        """Store the metadata fields as separate categorical columns.
         
+        A packed first column is unique per row, while each field on its own
+        repeats a handful of codes, so cached frames take far less memory.
+        _split_metadata turns the fields back into plain strings.
+        """
+        if not len(df.columns):
+            return df
+        source_columns = set(self._metadata_source_columns(df))
+        others = df[[col for col in df.columns if col not in source_columns]]
+        return pd.concat([self._split_metadata(df).astype('category'), others], axis=1)
+
+    def _clean_cells(self, df: pd.DataFrame, with_flags: bool) -> pd.DataFrame:
+        """Strip string cells and convert value columns to numbers."""
         # Clean whitespace from string columns and convert numeric columns
-        for col in df.columns:
+        for col in list(df.columns):
             if df[col].dtype == 'object':  # Only strip strings
                 df[col] = df[col].str.strip()
-            if col.isdigit():  # Convert year columns to numeric
-                df[col] = pd.to_numeric(df[col], errors='coerce')
+            if self._is_period_column(col):  # Convert value columns to numeric, keeping flags aside
+                values, flags = self._clean_value_column(df[col])
+                df[col] = values.mask(values == 0) if self.treat_zero_as_missing else values
+                if with_flags:
+                    df[f'{col}_flag'] = flags
+        return df
+
+    def _iter_clean_chunks(
+        self, 
+        file_path: Source, 
+        columns: Optional[List[str]] = None, 
+        chunksize: int = STREAM_CHUNK_ROWS
+    ) -> Iterator[pd.DataFrame]:
+        """Parse and clean a text file chunksize rows at a time, bypassing the cache.
+        
+        Parquet and Arrow files, and long files whose series span chunks, are
+        read in one piece.
+        """
+        if self.layout == 'long' or self._input_format(file_path) in COLUMNAR_FORMATS:
+            yield self._parse_df(file_path, None, False, columns)
+            return
+        
+        try:
+            for chunk in pd.read_csv(**self._csv_options(file_path, columns), chunksize=chunksize):
+                yield self._clean_cells(self._clean_column_names(chunk), with_flags=False)
+        except (pd.errors.ParserError, pd.errors.EmptyDataError, UnicodeDecodeError) as e:
+            raise FlowParseError(f"Could not parse {self._source_name(file_path)}: {e}") from e
+
+    def _input_format(self, source: Source) -> str:
+        """Tell from the extension whether a source is delimited text, Parquet, Arrow or ZIP."""
+        if isinstance(source, bytes):
+            return 'text'
+        
+        name = source.lower()
+        if name.endswith('.gz'):
+            name = name[:-len('.gz')]
+        if name.endswith(TEXT_EXTENSIONS):
+            return 'text'
+        if name.endswith('.parquet'):
+            return 'parquet'
+        if name.endswith(ARROW_EXTENSIONS):
+            return 'arrow'
+        if name.endswith('.zip'):
+            return 'zip'
+        raise FlowParseError(
+            f"Unsupported input format: {os.path.basename(source)} "
+            f"(expected {', '.join(TEXT_EXTENSIONS)}, optionally gzipped or zipped, .parquet, .arrow or .feather)"
+        )
+
+    def _unzip(self, file_path: str) -> bytes:
+        """Get the contents of the flow file inside a ZIP archive.
+        
+        The entry named by zip_entry is used if set, otherwise the only entry
+        with a text extension.
+        """
+        with zipfile.ZipFile(file_path) as archive:
+            names = archive.namelist()
+            if self.zip_entry is not None:
+                if self.zip_entry not in names:
+                    raise FlowParseError(f"{os.path.basename(file_path)} has no entry {self.zip_entry}")
+                return archive.read(self.zip_entry)
             
+            candidates = [
+                name for name in names
+                if name.lower().removesuffix('.gz').endswith(TEXT_EXTENSIONS)
+            ]
+            if not candidates:
+                raise FlowParseError(f"{os.path.basename(file_path)} contains no TSV file")
+            if len(candidates) > 1:
+                raise FlowParseError(
+                    f"{os.path.basename(file_path)} contains several TSV files ({', '.join(candidates)}); "
+                    f"choose one with zip_entry"
+                )
+            return archive.read(candidates[0])
+
+    def _column_name(self, col) -> str:
+        """Normalize a header so year columns are found by their integer value.
+        
+        Whitespace is trimmed, as is a time axis label like in 'geo\\time',
+        and numeric headers (2020, '2020.0', ' 2020') all become '2020'.
+        """
+        name = re.sub(TIME_AXIS_PATTERN, '', str(col).strip()).strip()
+        if re.fullmatch(r'\d+(\.0*)?', name):
+            name = str(int(name.split('.')[0]))
+        return name
+
+    def _read_raw_df(
+        self, 
+        file_path: Source, 
+        nrows: Optional[int] = None, 
+        columns: Optional[List[str]] = None
+    ) -> pd.DataFrame:
+        """Read a TSV file with its cells as-is, only cleaning the column names.
+        
+        If columns is given, value columns not listed are skipped while reading.
+        Long files are returned in the wide layout.
+        """
+        if self.layout == 'long':
+            df = self._clean_column_names(self._read_source_df(file_path))
+            return self._widen_long_df(file_path, df, nrows, columns)
+        df = self._clean_column_names(self._read_source_df(file_path, nrows, columns))
+        if self._input_format(file_path) in COLUMNAR_FORMATS:
+            self._check_metadata_columns(file_path, df)
+        return df
+
+    def _widen_long_df(
+        self,
+        file_path: Source,
+        df: pd.DataFrame,
+        nrows: Optional[int] = None,
+        columns: Optional[List[str]] = None
+    ) -> pd.DataFrame:
+        """Pivot a long file into one row per series with one column per year.
+        
+        Repeated series-year rows become separate series, so on_duplicate
+        treats them like repeated rows of a wide file.
+        """
+        missing = [col for col in LONG_LAYOUT_COLUMNS if col not in df.columns]
+        if missing:
+            raise SchemaMismatchError(
+                f"{self._source_name(file_path)} lacks the long layout columns {', '.join(missing)}"
+            )
+        
+        df = df.assign(**{
+            field: '' for field in self.metadata_columns if field not in df.columns
+        })
+        df = df.assign(year=df['year'].astype(str).str.strip())
+        if columns:
+            df = df[df['year'].isin(columns)]
+        
+        key = self.metadata_columns + ['year']
+        occurrence = df.groupby(key, sort=False).cumcount().rename('occurrence')
+        wide = df.set_index(key + [occurrence])['value'].unstack('year')
+        wide = wide.reset_index(self.metadata_columns).reset_index(drop=True)
+        years = sorted((col for col in wide.columns if col not in self.metadata_columns), key=str)
+        wide = wide[self.metadata_columns + years]
+        wide.columns = [str(col) for col in wide.columns]
+        return wide if nrows is None else wide.head(nrows)
+
+    def _read_source_df(
+        self, 
+        file_path: Source, 
+        nrows: Optional[int] = None, 
+        columns: Optional[List[str]] = None
+    ) -> pd.DataFrame:
+        """Read a flow file with its cells and column names exactly as stored."""
+        input_format = self._input_format(file_path)
+        if input_format in COLUMNAR_FORMATS:
+            df = pd.read_parquet(file_path) if input_format == 'parquet' else pd.read_feather(file_path)
+            df = df.iloc[self.skip_rows_after_header:].reset_index(drop=True)
+            usecols = self._usecols(columns)
+            if usecols is not None:
+                df = df[[col for col in df.columns if usecols(col)]]
+            if nrows is not None:
+                df = df.head(nrows)
+        else:
+            try:
+                df = pd.read_csv(**self._csv_options(file_path, columns), nrows=nrows)
+            except (pd.errors.ParserError, pd.errors.EmptyDataError, UnicodeDecodeError) as e:
+                raise FlowParseError(f"Could not parse {self._source_name(file_path)}: {e}") from e
         return df
 
+    def _check_metadata_columns(self, file_path: str, df: pd.DataFrame) -> None:
+        """Check that a columnar file holds the metadata fields as separate columns."""
+        if self._packed_column(df) is not None:
+            return  # Packed metadata in one column, as in the TSV layout
+        missing = [field for field in self.metadata_columns if field not in df.columns]
+        if missing:
+            raise SchemaMismatchError(
+                f"{self._source_name(file_path)} lacks the metadata columns {', '.join(missing)}"
+            )
+
+    def _usecols(self, columns: Optional[List[str]]):
+        """Get a column filter keeping the metadata and the listed value columns, or None for all."""
+        if not columns:
+            return None
+        wanted = set(columns)
+        return lambda col: self._column_name(col) in wanted or not self._is_period_column(self._column_name(col))
+
+    def _csv_options(self, file_path: Source, columns: Optional[List[str]] = None) -> Dict:
+        """Get the pd.read_csv arguments for a delimited text source."""
+        if self._input_format(file_path) == 'zip':
+            file_path = self._unzip(file_path)
+        separator = self.separator or self._detect_separator(file_path)
+        if self.skip_bad_rows:
+            file_path, skipped = self._drop_bad_rows(file_path, separator)
+            if skipped:
+                logger.warning("Skipped %d malformed rows", skipped)
+        compression = self._detect_compression(file_path)
+        return dict(
+            filepath_or_buffer=io.BytesIO(file_path) if isinstance(file_path, bytes) else file_path,
+            sep=separator,
+            engine='python',
+            dtype=str,  # Values are converted by _clean_value_column without float rounding
+            usecols=self._usecols(columns),
+            skiprows=range(1, self.skip_rows_after_header + 1),
+            compression=compression,
+            encoding=self.encoding,
+            memory_map=self.memory_map and compression is None and not isinstance(file_path, bytes)
+        )
+
+    def _drop_bad_rows(self, source: Source, separator: str) -> Tuple[bytes, int]:
+        """Remove the rows whose field count differs from the header's.
+        
+        Returns the uncompressed remaining contents and the number of rows
+        removed. Blank lines are kept, as read_csv skips them anyway.
+        """
+        with self._open_binary(source) as f:
+            data = f.read()
+        if data[:2] == GZIP_MAGIC:
+            data = gzip.decompress(data)
+        
+        lines = data.splitlines(keepends=True)
+        if not lines:
+            return data, 0
+        
+        sep = separator.encode(self.encoding)
+        expected = lines[0].count(sep)
+        head = 1 + self.skip_rows_after_header  # Rows skipped after the header are not data
+        kept = lines[:head] + [line for line in lines[head:] if not line.strip() or line.count(sep) == expected]
+        return b''.join(kept), len(lines) - len(kept)
+
+    def _malformed_rows(self, file_path: Source) -> int:
+        """Count the rows of a text file whose field count differs from the header's."""
+        input_format = self._input_format(file_path)
+        if input_format in COLUMNAR_FORMATS:
+            return 0
+        source = self._unzip(file_path) if input_format == 'zip' else file_path
+        return self._drop_bad_rows(source, self.separator or self._detect_separator(source))[1]
+
+    def _clean_column_names(self, df: pd.DataFrame) -> pd.DataFrame:
+        """Clean whitespace and a byte order mark (as decoded by the encoding) from column names."""
+        bom = codecs.BOM_UTF8.decode(self.encoding, errors='ignore')
+        names = []
+        for col in df.columns:
+            name = self._column_name(col)
+            # read_csv renames a repeated header '2020' to '2020.1'
+            mangled = re.fullmatch(r'(.+)\.\d+', name)
+            if mangled and self._is_period_column(mangled.group(1)) and mangled.group(1) in names:
+                name = mangled.group(1)
+            names.append(name)
+        df.columns = names
+        if bom and df.columns[0].startswith(bom):
+            df = df.rename(columns={df.columns[0]: df.columns[0][len(bom):].strip()})
+        if self.column_map:
+            df.columns = [self._mapped_name(col) for col in df.columns]
+        return self._resolve_duplicate_periods(df)
+
+    def _mapped_name(self, name: str) -> str:
+        """Rename a file's header, or each field of a packed metadata header, after column_map."""
+        fields = {header: field for field, header in self.column_map.items()}
+        if ',' in name:
+            return ','.join(fields.get(part, part) for part in name.split(','))
+        return fields.get(name, name)
+
+    def _resolve_duplicate_periods(self, df: pd.DataFrame) -> pd.DataFrame:
+        """Merge repeated period columns according to on_duplicate_year."""
+        names = list(df.columns)
+        duplicated = sorted({name for name in names if names.count(name) > 1 and self._is_period_column(name)})
+        if not duplicated:
+            return df
+        if self.on_duplicate_year == 'error':
+            raise SchemaMismatchError(
+                f"Repeated period columns: {', '.join(duplicated)}; "
+                f"set on_duplicate_year to 'sum', 'first' or 'last' to merge them"
+            )
+        
+        merged = {}
+        for name in duplicated:
+            positions = [i for i, col in enumerate(names) if col == name]
+            if self.on_duplicate_year == 'first':
+                merged[name] = df.iloc[:, positions[0]]
+            elif self.on_duplicate_year == 'last':
+                merged[name] = df.iloc[:, positions[-1]]
+            else:
+                values = [self._clean_value_column(df.iloc[:, i])[0] for i in positions]
+                merged[name] = pd.concat(values, axis=1).sum(axis=1, min_count=1)
+        
+        # Keep each repeated column at its first position
+        keep = [i for i, name in enumerate(names) if name not in merged or i == names.index(name)]
+        result = df.iloc[:, keep].copy()
+        for name, values in merged.items():
+            result[name] = values
+        return result
+
     def get_value_range(self, year: int) -> Dict[str, float]:
         """Get the minimum and maximum values for a given year."""
         imports_df = self._read_and_clean_df(self.imports_file)
@@ -58,14 +832,69 @@ class FIGAROParser:
         imports_df = self._read_and_clean_df(self.imports_file)
         exports_df = self._read_and_clean_df(self.exports_file)
         
-        # Get unique geo values from first column
+        # Get unique geo values from the metadata fields
         regions = set()
         for df in [imports_df, exports_df]:
-            first_col = df.iloc[:, 0]
-            regions.update(first_col.str.split(',').str[-1].unique())
+            regions.update(self._split_metadata(df)['geo'].dropna().unique())
         
         return sorted(list(regions))
 
+    def melt_flows(self, file_path: str) -> pd.DataFrame:
+        """Unpivot every year column of a flow file into long form.
+        
+        Returns one row per series and year with the metadata fields, an
+        integer 'year' and the 'value'. Non-year headers and NaN values are
+        dropped.
+        """
+        df = self.read_frame(file_path)
+        year_columns = [col for col in df.columns if col.isdigit()]
+        
+        long_df = df.melt(
+            id_vars=self.metadata_columns,
+            value_vars=year_columns,
+            var_name='year',
+            value_name='value'
+        ).dropna(subset=['value'])
+        long_df['year'] = long_df['year'].astype(int)
+        return long_df.reset_index(drop=True)
+
+    def merge_yearly_files(self, paths: List[Tuple[int, str]]) -> List[Tuple[str, str, int, float]]:
+        """Combine files holding one year each into long form as (nace, geo, year, value).
+        
+        paths pairs each year with its file. Each file must have exactly one
+        value column, whatever its header, or a SchemaMismatchError is
+        raised. Rows keep the order of paths, and NaN values are dropped.
+        """
+        rows = []
+        for year, file_path in paths:
+            df = self._read_and_clean_df(file_path)
+            metadata = self._split_metadata(df)
+            value_columns = [col for col in df.columns if col not in self.metadata_columns]
+            if len(value_columns) != 1:
+                raise SchemaMismatchError(
+                    f"{self._source_name(file_path)} should have a single value column, "
+                    f"found {len(value_columns)}: {', '.join(value_columns)}"
+                )
+            values = df[value_columns[0]]
+            if not self._is_period_column(value_columns[0]):
+                values = self._clean_value_column(values)[0]  # e.g. OBS_VALUE, not converted on read
+            rows.extend(
+                (nace, geo, year, float(value))
+                for nace, geo, value in zip(metadata['nace_r2'], metadata['geo'], values)
+                if not pd.isna(value)
+            )
+        return rows
+
+    def coverage_by_year(self, file_path: str) -> List[Tuple[int, int, int]]:
+        """Count the reported values of each year as (year, non-null count, total rows).
+        
+        Cells with the ':' missing marker or no value count as null. Years are
+        sorted in ascending order.
+        """
+        df = self.read_frame(file_path)
+        year_columns = sorted((col for col in df.columns if col.isdigit()), key=int)
+        return [(int(col), int(df[col].notna().sum()), len(df)) for col in year_columns]
+
     def truncate_nace_code(self, code: str, level: int) -> str:
         """Truncate NACE code to specified level."""
         if not code or level < 1:
@@ -75,48 +904,420 @@ class FIGAROParser:
 
     def get_available_years(self) -> List[int]:
         """Get list of available years in the dataset."""
-        df = self._read_and_clean_df(self.imports_file)
+        return self._file_years(self.imports_file)
+
+    def list_years(self, file_path: Source) -> List[int]:
+        """Get the sorted years of a flow file."""
+        return self._file_years(file_path)
+
+    def year_range(self, file_path: Source) -> Tuple[int, int]:
+        """Get the first and last years of a flow file, e.g. for slider bounds."""
+        years = self._file_years(file_path)
+        if not years:
+            raise SchemaMismatchError(f"{self._source_name(file_path)} has no year columns")
+        return years[0], years[-1]
+
+    def list_geos(self, file_path: Source) -> List[str]:
+        """Get the sorted distinct geo codes of a flow file."""
+        return sorted(self._split_metadata(self._read_and_clean_df(file_path))['geo'].dropna().unique())
+
+    def list_nace(self, file_path: Source) -> List[str]:
+        """Get the sorted distinct NACE codes of a flow file."""
+        return sorted(self._split_metadata(self._read_and_clean_df(file_path))['nace_r2'].dropna().unique())
+
+    def list_series_keys(self, file_path: Source) -> List[Tuple[str, ...]]:
+        """Get the sorted distinct metadata tuples of a flow file, e.g. to fill cascading filters.
+        
+        Tuples follow metadata_columns, (freq, nace_r2, c_exp, unit, geo) by
+        default. Value cells are never parsed, so this is cheaper than list_geos
+        or list_nace on large files.
+        """
+        keys = self._split_metadata(self._read_raw_df(file_path)).dropna().drop_duplicates()
+        return sorted(tuple(key) for key in keys.itertuples(index=False))
+
+    def validate_file(self, file_path: Source) -> None:
+        """Check cheaply that a flow file can be processed, raising a FlowError subclass if not.
+        
+        Only the header and the first SNIFF_LINES data rows are read. A
+        header that is not split into columns raises a FlowParseError (usually
+        a wrong separator); missing period columns or metadata fields that do
+        not match metadata_columns raise a SchemaMismatchError.
+        """
+        df = self._read_raw_df(file_path, nrows=SNIFF_LINES)
+        name = self._source_name(file_path)
+        if len(df.columns) < 2:
+            raise FlowParseError(f"Could not split the header of {name} into columns; check the separator")
+        if not any(self._is_period_column(col) for col in df.columns):
+            raise SchemaMismatchError(f"{name} has no year or period columns")
+        
+        metadata, _ = self._header_layout(file_path)
+        if len(metadata) != len(self.metadata_columns):
+            raise SchemaMismatchError(
+                f"{name} has {len(metadata)} metadata fields but {len(self.metadata_columns)} names "
+                f"were configured: {', '.join(self.metadata_columns)}"
+            )
+        mismatched = self._metadata_mismatches(df)
+        if mismatched:
+            raise SchemaMismatchError(
+                f"Rows {', '.join(str(row) for row in mismatched)} of {name} do not have "
+                f"{len(self.metadata_columns)} metadata fields"
+            )
+
+    def detect_frequency(self, file_path: Source) -> List[str]:
+        """Get the sorted distinct freq codes of a flow file, e.g. ['A'] or ['A', 'M']."""
+        if 'freq' not in self.metadata_columns:
+            raise SchemaMismatchError(f"No freq field among the metadata columns: {', '.join(self.metadata_columns)}")
+        return sorted(self._split_metadata(self._read_and_clean_df(file_path))['freq'].dropna().unique())
+
+    def unit_distribution(self, file_path: Source) -> List[Tuple[str, int]]:
+        """Count the rows per unit of a flow file, most frequent first, e.g. to pick a unit_filter.
+        
+        A file listing more than one unit cannot be summed as is; see
+        MixedUnitsError.
+        """
+        if 'unit' not in self.metadata_columns:
+            raise SchemaMismatchError(f"No unit field among the metadata columns: {', '.join(self.metadata_columns)}")
+        counts = self._split_metadata(self._read_raw_df(file_path))['unit'].value_counts()
+        return sorted(((unit, int(count)) for unit, count in counts.items()), key=lambda item: (-item[1], item[0]))
+
+    def single_unit(self, df: pd.DataFrame, source: str, unit_filter: Optional[str] = None) -> pd.DataFrame:
+        """Keep the rows of unit_filter, if given, and check the rest share one unit before summing.
+        
+        Values in different units (e.g. MIO_EUR and THS_T) cannot be added up,
+        so rows mixing them raise a MixedUnitsError naming source. Frames of
+        custom metadata layouts without a unit field are returned as is.
+        """
+        if 'unit' not in df.columns:
+            return df
+        if unit_filter is not None:
+            df = df[df['unit'] == unit_filter]
+        units = sorted(df['unit'].dropna().unique())
+        if len(units) > 1:
+            raise MixedUnitsError(f"{source} mixes units {', '.join(units)}; choose one with unit_filter")
+        return df
+
+    def _file_years(self, file_path: Source) -> List[int]:
+        """Get the sorted years of a flow file from its header only."""
+        df = self._read_and_clean_df(file_path, nrows=0)
         # Get all numeric columns (year columns)
         year_columns = [col for col in df.columns if col.isdigit()]
         return sorted([int(year) for year in year_columns])
 
+    def resolve_year_column(self, file_path: Source, year: int) -> Optional[str]:
+        """Get the header of the column read for a year, or None if the file lacks it.
+        
+        Headers match the year the same way get_flow_data does, after trimming
+        whitespace and integer conversion, so ' 2020' or '2020.0' are returned
+        as written in the file for a request of 2020.
+        """
+        for header in self._read_source_df(file_path, nrows=0).columns:
+            if self._column_name(header) == str(year):
+                return str(header)
+        return None
+
+    def _format_years(self, years: List[int]) -> str:
+        """Format years compactly, as a range when they are contiguous."""
+        if len(years) > 1 and years == list(range(years[0], years[-1] + 1)):
+            return f"{years[0]}..{years[-1]}"
+        return ', '.join(str(y) for y in years)
+
+    def _check_year_available(self, year: int, file_paths: List[Source]) -> None:
+        """Raise a MissingYearError if any of the files lacks a column for the year."""
+        for file_path in file_paths:
+            years = self._file_years(file_path)
+            if year not in years:
+                raise MissingYearError(
+                    f"Year {year} not found in {self._source_name(file_path)}; "
+                    f"available years: {self._format_years(years)}"
+                )
+
+    def _header_layout(self, file_path: Source) -> Tuple[List[str], List[str]]:
+        """Get the metadata field names and period columns of a file from its header."""
+        columns = list(self._read_raw_df(file_path, nrows=0).columns)
+        metadata = [field for col in columns if not self._is_period_column(col) for field in col.split(',')]
+        periods = [col for col in columns if self._is_period_column(col)]
+        return metadata, periods
+
+    def _check_matching_schemas(self, imports_path: Source, exports_path: Source) -> None:
+        """Raise a SchemaMismatchError listing how the headers of two flow files differ."""
+        imports_name, exports_name = self._source_name(imports_path), self._source_name(exports_path)
+        (imports_fields, imports_periods) = self._header_layout(imports_path)
+        (exports_fields, exports_periods) = self._header_layout(exports_path)
+        
+        differences = []
+        for label, imports_items, exports_items in [
+            ('metadata fields', imports_fields, exports_fields),
+            ('period columns', imports_periods, exports_periods)
+        ]:
+            only_imports = [item for item in imports_items if item not in exports_items]
+            only_exports = [item for item in exports_items if item not in imports_items]
+            if only_imports:
+                differences.append(f"{label} only in {imports_name}: {', '.join(only_imports)}")
+            if only_exports:
+                differences.append(f"{label} only in {exports_name}: {', '.join(only_exports)}")
+            if not only_imports and not only_exports and imports_items != exports_items:
+                differences.append(f"{label} are in a different order")
+        
+        if differences:
+            raise SchemaMismatchError(
+                f"{imports_name} and {exports_name} do not share a layout: {'; '.join(differences)}"
+            )
+
+    def _packed_column(self, df: pd.DataFrame):
+        """Get the header of the column packing all metadata fields (e.g. 'freq,nace_r2,...'), or None."""
+        return next((col for col in df.columns if ',' in str(col)), None)
+
+    def _metadata_source_columns(self, df: pd.DataFrame) -> List:
+        """Get the columns _split_metadata reads the metadata fields from."""
+        packed = self._packed_column(df)
+        if packed is not None:
+            return [packed]
+        # One column per field, found by name when the header has them all and by position otherwise
+        if all(field in df.columns for field in self.metadata_columns):
+            return list(self.metadata_columns)
+        return list(df.columns[:len(self.metadata_columns)])
+
+    def _split_metadata(self, df: pd.DataFrame) -> pd.DataFrame:
+        """Split the packed metadata column of a flow file into its metadata fields."""
+        packed = self._packed_column(df)
+        if packed is None:
+            field_columns = df[self._metadata_source_columns(df)]
+            return pd.DataFrame({
+                field: field_columns.iloc[:, idx].astype(object)  # Categorical fields back to strings
+                for idx, field in enumerate(self.metadata_columns)
+            })
+        
+        # The packed column has the format freq,nace_r2,c_exp,unit,geo by default
+        first_col_parts = df[packed].str.split(',')
+        
+        if len(df) > 0:
+            field_count = len(first_col_parts.iloc[0])
+            if field_count != len(self.metadata_columns):
+                raise SchemaMismatchError(
+                    f"First data row has {field_count} metadata fields but "
+                    f"{len(self.metadata_columns)} names were configured: {', '.join(self.metadata_columns)}"
+                )
+        if self.strict_metadata:
+            mismatched = self._metadata_mismatches(df)
+            if mismatched:
+                raise SchemaMismatchError(
+                    f"Rows {', '.join(str(row) for row in mismatched)} do not have "
+                    f"{len(self.metadata_columns)} metadata fields"
+                )
+        
+        # Fields are trimmed so ' DE' still matches a geo filter on 'DE'
+        return pd.DataFrame({
+            field: first_col_parts.str[idx].str.strip()
+            for idx, field in enumerate(self.metadata_columns)
+        })
+
+    def _metadata_mismatches(self, df: pd.DataFrame) -> List[int]:
+        """Get the indices of the rows whose packed metadata field count differs from metadata_columns."""
+        packed = self._packed_column(df)
+        if packed is None:
+            return []  # One column per field, so pandas already pads or rejects short rows
+        counts = df[packed].str.split(',').str.len()
+        return [int(row) for row in counts.index[counts.notna() & (counts != len(self.metadata_columns))]]
+
+    def metadata_mismatch_rows(self, file_path: Source) -> List[int]:
+        """Get the indices of a flow file's data rows with too few or too many metadata fields."""
+        return self._metadata_mismatches(self._read_raw_df(file_path))
+
+    def read_frame(self, file_path: Source, years: Optional[List[int]] = None) -> pd.DataFrame:
+        """Read a flow file with one column per metadata field and per year.
+        
+        If years is given, only those year columns are read from the file.
+        """
+        df = self._read_and_clean_df(file_path, columns=[str(year) for year in years] if years else None)
+        value_columns = [col for col in df.columns if self._is_period_column(col)]
+        return pd.concat([self._split_metadata(df), df[value_columns]], axis=1)
+
+    def series_count(self, file_path: Source) -> int:
+        """Get the number of series (data rows) of a flow file, e.g. to decide on streaming or paging.
+        
+        Together with estimated_memory this sizes a file before any flows are
+        extracted; both share the cached parse.
+        """
+        return len(self._read_and_clean_df(file_path))
+
+    def estimated_memory(self, file_path: Source, categorical: bool = True) -> int:
+        """Estimate the bytes a parsed flow file takes in memory.
+        
+        The estimate counts the frame's columns including string contents but
+        not allocator or interpreter overhead, so it is approximate. By default
+        it measures the cached frame with categorical metadata; categorical=False
+        measures the packed metadata column as read, for comparison.
+        """
+        if categorical:
+            df = self._read_and_clean_df(file_path)
+        else:
+            df = self._clean_cells(self._read_raw_df(file_path), with_flags=False)
+        return int(df.memory_usage(deep=True).sum())
+
+    def describe_schema(self, file_path: Source) -> List[Tuple[str, str]]:
+        """Get the (column, dtype) pairs of a file as read_frame returns it.
+        
+        Year columns should come out as Int64 when they hold only whole
+        numbers and as float64 otherwise; anything else suggests cells that
+        could not be parsed as numbers.
+        """
+        return [(col, str(dtype)) for col, dtype in self.read_frame(file_path).dtypes.items()]
+
+    def get_year_frame(self, file_path: Source, year: int) -> pd.DataFrame:
+        """Get the metadata fields and values of a single year from a flow file."""
+        year_str = str(year)
+        df = self._read_and_clean_df(file_path, columns=[year_str])
+        
+        if year_str not in df.columns:
+            self._check_year_available(year, [file_path])
+        
+        return self._value_frame(df, year_str)
+
+    def _stream_year_frame(self, file_path: Source, year: int, chunksize: int = STREAM_CHUNK_ROWS) -> pd.DataFrame:
+        """Get the same frame as get_year_frame while parsing only chunksize rows at a time.
+        
+        Only the metadata fields and values are kept from each chunk, so peak
+        memory stays well below that of parsing the whole file.
+        """
+        year_str = str(year)
+        self._check_year_available(year, [file_path])
+        
+        frames = [
+            self._value_frame(chunk, year_str)
+            for chunk in self._iter_clean_chunks(file_path, [year_str], chunksize)
+        ]
+        if not frames:
+            return self.get_year_frame(file_path, year)
+        return pd.concat(frames, ignore_index=True)
+
+    def _value_frame(self, df: pd.DataFrame, column: str) -> pd.DataFrame:
+        """Get the metadata fields alongside a single value column."""
+        # Create initial dataframe with variables and values
+        result_df = self._split_metadata(df)
+        result_df['value'] = df[column]
+        return result_df
+
+    def _units(self, df: pd.DataFrame) -> pd.Series:
+        """Get the unit of each row, or None for custom metadata layouts without a unit field."""
+        if 'unit' in df.columns:
+            return df['unit']
+        return pd.Series(None, index=df.index, dtype=object)
+
+    def _resolve_duplicates(self, df: pd.DataFrame, on_duplicate: str) -> pd.DataFrame:
+        """Resolve rows sharing geo, nace_r2 and unit by summing, keeping the first, or raising.
+        
+        Custom metadata layouts lacking some of these fields key series on
+        the ones they have.
+        """
+        if on_duplicate not in DUPLICATE_POLICIES:
+            raise ValueError(f"on_duplicate must be one of {', '.join(DUPLICATE_POLICIES)}, got {on_duplicate}")
+        
+        series_key = [field for field in SERIES_KEY if field in df.columns]
+        if not series_key:
+            return df
+        duplicated = df.duplicated(subset=series_key, keep=False)
+        if not duplicated.any():
+            return df
+        
+        if on_duplicate == 'error':
+            offending = sorted({tuple(key) for key in df.loc[duplicated, series_key].values.tolist()})
+            raise DuplicateSeriesError(
+                f"Duplicate series for ({', '.join(series_key)}): "
+                f"{'; '.join(', '.join(str(part) for part in key) for key in offending)}"
+            )
+        
+        if on_duplicate == 'first':
+            return df.drop_duplicates(subset=series_key, keep='first')
+        
+        summed = df.groupby(series_key, sort=False)['value'].sum(min_count=1)
+        result_df = df.drop_duplicates(subset=series_key, keep='first').set_index(series_key)
+        result_df['value'] = summed
+        return result_df.reset_index()[df.columns]
+
     def _process_flow_file(
         self, 
-        file_path: str, 
+        file_path: Source, 
         year: int, 
         flow_type: str,
-        min_value: float = 0.0,
+        min_value: Optional[float] = 0.0,
         drop_nan: bool = True,
         nace_level: int = None,
-        regions: List[str] = None
-    ) -> List[Dict[str, Union[str, float]]]:
+        regions: List[str] = None,
+        geo: Optional[str] = None,
+        top_n: Optional[int] = None,
+        nace_prefix: Optional[str] = None,
+        on_duplicate: str = 'error',
+        nan_mode: str = 'skip',
+        scale: float = 1.0,
+        streaming: bool = False,
+        round_decimals: Optional[int] = None,
+        reject_negative: bool = False,
+        with_geo: bool = False,
+        with_nace_labels: bool = False,
+        freq_filter: Optional[str] = None,
+        error_on_empty_year: bool = False,
+        include_nace: Optional[List[str]] = None,
+        exclude_nace: Optional[List[str]] = None
+    ) -> List[Dict[str, Union[str, float, None]]]:
         """Process a single flow file."""
-        df = self._read_and_clean_df(file_path)
+        if nan_mode not in NAN_MODES:
+            raise ValueError(f"nan_mode must be one of {', '.join(NAN_MODES)}, got {nan_mode}")
         
-        # Get first column (which has the format freq,nace_r2,c_exp,unit,geo)
-        first_col = df.iloc[:, 0]
-        first_col_parts = first_col.str.split(',')
+        year_frame = self._stream_year_frame(file_path, year) if streaming else self.get_year_frame(file_path, year)
+        result_df = self._resolve_duplicates(year_frame, on_duplicate)
+        logger.debug("Matched year column %d in %s", year, self._source_name(file_path))
         
-        year_str = str(year)
-        if year_str not in df.columns:
-            raise ValueError(f"Year {year} not found in data. Available years: {', '.join(str(y) for y in self.get_available_years())}")
+        if len(result_df) and result_df['value'].isna().all():
+            if error_on_empty_year:
+                raise EmptyYearError(f"Year {year} has no reported values in {self._source_name(file_path)}")
+            logger.warning("Year %d has no reported values in %s", year, self._source_name(file_path))
         
-        # Create initial dataframe with variables and values
-        result_df = pd.DataFrame({
-            'nace_r2': first_col_parts.str[1],  # Second element is nace_r2
-            'value': df[year_str],
-            'geo': first_col_parts.str[4]  # Last element is geo
-        })
+        if freq_filter is not None:
+            if not (result_df['freq'] == freq_filter).any():
+                raise ValueError(f"Frequency {freq_filter} not found in {self._source_name(file_path)}")
+            result_df = result_df[result_df['freq'] == freq_filter]
         
-        # Apply filters
+        # Apply filters; missing values themselves are left to nan_mode
         if drop_nan:
-            result_df = result_df.dropna()
+            rows_before = len(result_df)
+            if nan_mode == 'skip':
+                result_df = result_df.dropna()
+            else:
+                result_df = result_df.dropna(subset=[col for col in result_df.columns if col != 'value'])
+            logger.debug("Skipped %d rows with missing values", rows_before - len(result_df))
         
-        if min_value > 0:
-            result_df = result_df[result_df['value'] >= min_value]
+        # Compare magnitudes so large negative values are not dropped
+        if min_value is not None and min_value > 0:
+            result_df = result_df[result_df['value'].abs() >= min_value]
             
         if regions:
             result_df = result_df[result_df['geo'].isin(regions)]
+            
+        if geo is not None:
+            result_df = result_df[result_df['geo'] == geo]
+            
+        if nace_prefix and nace_prefix.strip():
+            result_df = result_df.assign(nace_r2=result_df['nace_r2'].str.strip())
+            matches = result_df['nace_r2'].str.upper().str.startswith(nace_prefix.strip().upper(), na=False)
+            result_df = result_df[matches]
+        
+        if include_nace is not None:
+            codes = result_df['nace_r2'].str.strip().str.upper()
+            result_df = result_df[codes.isin({code.strip().upper() for code in include_nace})]
+        if exclude_nace is not None:
+            codes = result_df['nace_r2'].str.strip().str.upper()
+            result_df = result_df[~codes.isin({code.strip().upper() for code in exclude_nace})]
+        
+        # Trade values are never negative, so these usually point at bad input
+        negative = result_df[result_df['value'] < 0]
+        if not negative.empty:
+            if reject_negative:
+                rows = ', '.join(
+                    f"({nace}, {geo}, {value})"
+                    for nace, geo, value in zip(negative['nace_r2'], negative['geo'], negative['value'])
+                )
+                raise NegativeValueError(f"Negative values in {self._source_name(file_path)} for {year}: {rows}")
+            logger.warning("%d negative values in %s for %d", len(negative), self._source_name(file_path), year)
         
         # Apply NACE level aggregation if specified
         if nace_level is not None and nace_level > 0:
@@ -124,54 +1325,838 @@ class FIGAROParser:
                 lambda x: x[:nace_level] if x else x
             )
             # Aggregate values by truncated NACE code and sum the values
-            result_df = result_df.groupby(['nace_r2', 'geo'])['value'].sum().reset_index()
+            key = [field for field in ['nace_r2', 'geo', 'unit'] if field in result_df.columns]
+            result_df = result_df.groupby(key)['value'].sum(min_count=1).reset_index()
+        
+        # Exclude zero values, keeping missing ones only when nan_mode asks for them
+        missing = result_df['value'].isna()
+        result_df = result_df[(missing & (nan_mode != 'skip')) | (result_df['value'] != 0)]
         
         # Convert to the required format for Sankey diagram
-        return [
+        flows = [
             {
-                'source': row['nace_r2'],
+                'source': nace,
                 'target': flow_type,
-                'value': float(row['value'])
+                'unit': unit,
+                'value': (0.0 if nan_mode == 'zero' else None) if pd.isna(value) else self._scaled_value(value, scale)
             }
-            for _, row in result_df.iterrows()
-            if not pd.isna(row['value']) and row['value'] > 0  # Exclude zero values
+            for nace, unit, value in zip(result_df['nace_r2'], self._units(result_df), result_df['value'])
         ]
+        if with_geo:
+            for flow, geo_code in zip(flows, result_df['geo']):
+                flow['geo'] = geo_code
+        if with_nace_labels:
+            for flow in flows:
+                flow['label'] = nace_label(flow['source'])
+        
+        if round_decimals is not None:
+            for flow in flows:
+                if flow['value'] is not None:
+                    flow['value'] = round(flow['value'], round_decimals)
+        
+        # Keep only the largest flows, breaking ties by NACE code
+        if top_n is not None:
+            flows = sorted(flows, key=lambda flow: (-abs(flow['value'] or 0.0), flow['source']))[:top_n]
+        
+        return flows
+
+    def _scaled_value(self, value, scale: float) -> Union[int, float]:
+        """Divide a value by scale, keeping unscaled whole numbers as exact ints."""
+        if scale == 1 and isinstance(value, (int, np.integer)):
+            return int(value)
+        return float(value) / scale
 
     def get_flow_data(
         self, 
         year: int, 
-        min_value: float = 0.0,
+        min_value: Optional[float] = 0.0,
         drop_nan: bool = True,
         nace_level: int = None,
         regions: List[str] = None,
         include_imports: bool = True,
+        include_exports: bool = True,
+        geo: Optional[str] = None,
+        top_n: Optional[int] = None,
+        nace_prefix: Optional[str] = None,
+        on_duplicate: str = 'error',
+        nan_mode: str = 'skip',
+        scale: float = 1.0,
+        streaming: bool = False,
+        round_decimals: Optional[int] = None,
+        strict: bool = True,
+        reject_negative: bool = False,
+        max_threads: Optional[int] = None,
+        with_geo: bool = False,
+        with_nace_labels: bool = False,
+        freq_filter: Optional[str] = None,
+        import_label: str = IMPORTS_FLOW,
+        export_label: str = EXPORTS_FLOW,
+        sort_by: Optional[str] = None,
+        descending: bool = True,
+        error_on_empty_year: bool = False,
+        include_nace: Optional[List[str]] = None,
+        exclude_nace: Optional[List[str]] = None
+    ) -> List[Dict[str, Union[str, float, None]]]:
+        """Get filtered trade flow data for specified year.
+        
+        min_value drops flows whose absolute value is below it; None or 0
+        disables the threshold. Each flow carries the unit of its value (e.g. MIO_EUR) so callers can
+        avoid mixing incompatible measures. If geo is given, only flows of
+        that single country are returned. top_n limits imports and exports
+        separately to their largest flows. nace_prefix keeps one branch of
+        the NACE hierarchy (e.g. 'C' or 'C10'), ignoring case, while
+        include_nace keeps only the listed codes and exclude_nace drops the
+        listed ones (applied after include_nace), both ignoring case and
+        surrounding whitespace. with_geo adds
+        each flow's country as 'geo', e.g. for per-country stacked charts.
+        with_nace_labels adds the NACE description as 'label'; unknown codes
+        are labelled with the code itself. freq_filter keeps the series of
+        one frequency (e.g. 'A' in files mixing annual and monthly rows).
+        import_label and export_label replace the 'Total Imports' and 'Total
+        Exports' targets, e.g. to localize the diagram.
+        
+        Flows come in file order, imports first, unless sort_by is 'value',
+        'nace' or 'flow_type'; descending (the default) puts the largest
+        value or last code first. Ties are ordered by NACE code, flow type and
+        unit, and missing values always come last.
+        
+        Series repeated with the same geo, nace_r2 and unit raise a
+        DuplicateSeriesError unless on_duplicate is 'sum' or 'first'.
+        
+        Missing values are dropped by default (nan_mode 'skip'). 'zero'
+        reports them as 0.0, which suits stacked charts but understates any
+        sum computed from the flows; 'keep' returns them with a None value.
+        
+        Returned values are divided by scale (e.g. 1_000_000 for millions)
+        after all filtering, so min_value still applies to the raw values.
+        Scaling does not change the reported unit.
+        
+        round_decimals rounds the scaled values with Python's round(), which
+        rounds halves to even. None leaves them unrounded.
+        
+        streaming parses the files in chunks rather than whole, bounding
+        memory for very large bulk downloads at the cost of skipping the
+        frame cache.
+        
+        Negative values are logged as a warning, or raise a
+        NegativeValueError listing them when reject_negative is set. Likewise
+        a year whose column holds no values at all (e.g. only ':') is logged,
+        or raises an EmptyYearError with error_on_empty_year, so it is not
+        mistaken for filters matching nothing.
+        
+        When both files are read, their headers must list the same metadata
+        fields and periods in the same order, or a SchemaMismatchError names
+        the differences. With strict=False only the requested year has to be
+        present in both.
+        
+        max_threads caps the threads parsing imports and exports side by side
+        (by default one per file, or the configure_threadpool size); 1
+        processes them one after the other with identical results. Parquet
+        reads may still use pyarrow's own thread pool.
+        """
+        if not scale > 0:
+            raise ValueError(f"scale must be positive, got {scale}")
+        if round_decimals is not None and round_decimals < 0:
+            raise ValueError(f"round_decimals must not be negative, got {round_decimals}")
+        if max_threads is not None and max_threads < 1:
+            raise ValueError(f"max_threads must be at least 1, got {max_threads}")
+        if sort_by is not None and sort_by not in SORT_KEYS:
+            raise ValueError(f"sort_by must be one of {', '.join(SORT_KEYS)}, got {sort_by}")
+        
+        if strict and include_imports and include_exports:
+            self._check_matching_schemas(self.imports_file, self.exports_file)
+        
+        # Fail fast with the available years before any row processing
+        self._check_year_available(
+            year,
+            [file_path for file_path, _ in self._flow_files(include_imports, include_exports)]
+        )
+        
+        if geo is not None and geo not in self.get_available_regions():
+            raise ValueError(f"Geo code {geo} not found in imports or exports data")
+        
+        # Parse imports and exports concurrently; result() re-raises any worker error
+        flow_files = self._flow_files(include_imports, include_exports)
+        labels = {IMPORTS_FLOW: import_label, EXPORTS_FLOW: export_label}
+        flows = []
+        workers = max(len(flow_files), 1)
+        if max_threads is None:
+            max_threads = _THREADPOOL_SIZE
+        if max_threads is not None:
+            workers = min(workers, max_threads)
+        with ThreadPoolExecutor(max_workers=workers) as executor:
+            futures = [
+                executor.submit(
+                    self._process_flow_file,
+                    file_path,
+                    year,
+                    labels[flow_type],
+                    min_value=min_value,
+                    drop_nan=drop_nan,
+                    nace_level=nace_level,
+                    regions=regions,
+                    geo=geo,
+                    top_n=top_n,
+                    nace_prefix=nace_prefix,
+                    on_duplicate=on_duplicate,
+                    nan_mode=nan_mode,
+                    scale=scale,
+                    streaming=streaming,
+                    round_decimals=round_decimals,
+                    reject_negative=reject_negative,
+                    with_geo=with_geo,
+                    with_nace_labels=with_nace_labels,
+                    freq_filter=freq_filter,
+                    error_on_empty_year=error_on_empty_year,
+                    include_nace=include_nace,
+                    exclude_nace=exclude_nace
+                )
+                for file_path, flow_type in flow_files
+            ]
+            for future in futures:
+                flows.extend(future.result())
+        
+        if sort_by is not None:
+            flows = self._sort_flows(flows, SORT_KEYS[sort_by], descending)
+        logger.info("Extracted %d flows for %d", len(flows), year)
+        return flows
+
+    def _sort_flows(self, flows: List[Dict], key: str, descending: bool) -> List[Dict]:
+        """Sort flows on one field with a stable NACE code, flow type and unit tie-breaker."""
+        flows = sorted(flows, key=lambda flow: (flow['source'], flow['target'], str(flow['unit'])))
+        present = [flow for flow in flows if flow[key] is not None]
+        missing = [flow for flow in flows if flow[key] is None]
+        return sorted(present, key=lambda flow: flow[key], reverse=descending) + missing
+
+    async def get_flow_data_async(self, year: int, **filters) -> List[Dict[str, Union[str, float, None]]]:
+        """Await get_flow_data without blocking the event loop of an async server.
+        
+        The extraction runs in a worker thread; pandas releases the GIL in
+        much of its parsing and grouping, so other coroutines and threads keep
+        running meanwhile. Keyword arguments are passed to get_flow_data.
+        """
+        return await asyncio.to_thread(self.get_flow_data, year, **filters)
+
+    def get_flow_data_with_options(
+        self, year: int, options: Union[FlowOptions, Dict, None] = None
+    ) -> List[Dict[str, Union[str, float, None]]]:
+        """Get the flows of a year with filters given as FlowOptions or a dict of them."""
+        if options is None:
+            options = FlowOptions()
+        elif not isinstance(options, FlowOptions):
+            options = FlowOptions.from_dict(options)
+        return self.get_flow_data(year, **asdict(options))
+
+    def get_flow_data_typed(self, year: int, **filters) -> List[Flow]:
+        """Get the flows of a year as Flow objects instead of dicts.
+        
+        Keyword arguments are passed to get_flow_data as filters.
+        """
+        return [
+            Flow(nace=flow['source'], flow_type=flow['target'], value=flow['value'], unit=flow['unit'])
+            for flow in self.get_flow_data(year, **filters)
+        ]
+
+    def iter_flow_data(
+        self,
+        year: int,
+        min_value: Optional[float] = 0.0,
+        include_imports: bool = True,
+        include_exports: bool = True,
+        chunksize: int = STREAM_CHUNK_ROWS
+    ) -> Iterator[Dict[str, Union[str, float]]]:
+        """Yield the flows of a year one by one, parsing the files chunksize rows at a time.
+        
+        Flows match get_flow_data with its default filters and the given
+        min_value, but only one chunk of each file is held in memory, so very
+        large results can be consumed without materializing a list. Missing
+        values and zeros are skipped; duplicate series are not detected across
+        chunks and are yielded as they appear.
+        """
+        flow_files = self._flow_files(include_imports, include_exports)
+        self._check_year_available(year, [file_path for file_path, _ in flow_files])
+        
+        year_str = str(year)
+        for file_path, flow_type in flow_files:
+            for chunk in self._iter_clean_chunks(file_path, [year_str], chunksize):
+                frame = self._value_frame(chunk, year_str).dropna()
+                frame = frame[frame['value'] != 0]
+                if min_value is not None and min_value > 0:
+                    frame = frame[frame['value'].abs() >= min_value]
+                for nace, unit, value in zip(frame['nace_r2'], self._units(frame), frame['value']):
+                    yield {'source': nace, 'target': flow_type, 'unit': unit, 'value': self._scaled_value(value, 1.0)}
+
+    def get_flow_data_grouped(self, year: int, **filters) -> Dict[str, List[Tuple[str, float]]]:
+        """Get the flows of a year as (nace, value) pairs grouped by flow type.
+        
+        Returns {'imports': [...], 'exports': [...]} with each list sorted by
+        value, largest first, and missing values last. Keyword arguments are
+        passed to get_flow_data as filters.
+        """
+        labels = {
+            filters.get('import_label', IMPORTS_FLOW): 'imports',
+            filters.get('export_label', EXPORTS_FLOW): 'exports',
+        }
+        grouped = {'imports': [], 'exports': []}
+        for flow in self.get_flow_data(year, **filters):
+            grouped[labels[flow['target']]].append((flow['source'], flow['value']))
+        
+        for pairs in grouped.values():
+            pairs.sort(key=lambda pair: (pd.isna(pair[1]), -pair[1] if pd.notna(pair[1]) else 0))
+        return grouped
+
+    def get_flow_page(
+        self,
+        year: int,
+        offset: int = 0,
+        limit: Optional[int] = None,
+        **filters
+    ) -> Tuple[List[Dict[str, Union[str, float, None]]], int]:
+        """Get one page of the flows of a year together with the total flow count.
+        
+        Flows are sorted by NACE code, then flow type and unit, so pages are
+        stable between calls. An offset past the end gives an empty page.
+        Extra keyword arguments are passed to get_flow_data as filters.
+        """
+        if offset < 0:
+            raise ValueError(f"offset must not be negative, got {offset}")
+        if limit is not None and limit < 0:
+            raise ValueError(f"limit must not be negative, got {limit}")
+        
+        flows = self._sort_flows(self.get_flow_data(year, **filters), 'source', False)
+        end = None if limit is None else offset + limit
+        return flows[offset:end], len(flows)
+
+    def get_flow_data_range(
+        self,
+        start_year: int,
+        end_year: int,
+        include_imports: bool = True,
+        include_exports: bool = True,
+        progress: Optional[ProgressCallback] = None,
+        deflators: Optional[Dict[int, float]] = None
+    ) -> List[Dict[str, Union[str, int, float]]]:
+        """Get trade flows for every year between start_year and end_year (inclusive).
+        
+        Each file is parsed once; years without a column are skipped. progress
+        is called with (completed, total) after each year of each file, and an
+        exception it raises aborts the extraction.
+        
+        deflators maps each year to a price index (e.g. 1.05 for prices 5%
+        above the base year) that its values are divided by, giving real
+        values at the prices of a single base year. Every extracted year needs
+        a positive deflator.
+        """
+        if deflators is not None:
+            invalid = sorted(year for year, deflator in deflators.items() if not deflator > 0)
+            if invalid:
+                raise ValueError(f"Deflators must be positive; got {', '.join(str(y) for y in invalid)}")
+        
+        flow_files = self._flow_files(include_imports, include_exports)
+        years = range(start_year, end_year + 1)
+        total = len(flow_files) * len(years)
+        completed = 0
+        
+        flows = []
+        for file_path, flow_type in flow_files:
+            df = self._read_and_clean_df(file_path)
+            metadata = self._split_metadata(df)
+            
+            for year in years:
+                completed += 1
+                year_str = str(year)
+                if year_str not in df.columns:
+                    if progress is not None:
+                        progress(completed, total)
+                    continue
+                if deflators is not None and year not in deflators:
+                    raise ValueError(f"No deflator given for {year}")
+                deflator = deflators[year] if deflators is not None else 1.0
+                flows.extend(
+                    {
+                        'source': nace,
+                        'target': flow_type,
+                        'unit': unit,
+                        'year': year,
+                        'value': float(value) / deflator
+                    }
+                    for nace, unit, value in zip(metadata['nace_r2'], self._units(metadata), df[year_str])
+                    if not pd.isna(nace) and not pd.isna(value) and value != 0  # Exclude zero values
+                )
+                if progress is not None:
+                    progress(completed, total)
+        
+        return flows
+
+    def get_flow_data_years(
+        self,
+        years: List[int],
+        include_imports: bool = True,
+        include_exports: bool = True
+    ) -> List[Dict[str, Union[str, int, float]]]:
+        """Get trade flows for a list of possibly non-contiguous years.
+        
+        Each file is parsed once. Flows are grouped by year in the requested
+        order. Unlike get_flow_data_range, years missing from a file raise a
+        MissingYearError naming all of them.
+        """
+        flow_files = self._flow_files(include_imports, include_exports)
+        for file_path, _ in flow_files:
+            available = self._file_years(file_path)
+            missing = [year for year in years if year not in available]
+            if missing:
+                raise MissingYearError(
+                    f"Year(s) {', '.join(str(y) for y in missing)} not found in {self._source_name(file_path)}; "
+                    f"available years: {self._format_years(available)}"
+                )
+        
+        frames = [
+            (self._read_and_clean_df(file_path, columns=[str(year) for year in years]), flow_type)
+            for file_path, flow_type in flow_files
+        ]
+        
+        flows = []
+        for year in years:
+            for df, flow_type in frames:
+                year_frame = self._value_frame(df, str(year))
+                flows.extend(
+                    {
+                        'source': nace,
+                        'target': flow_type,
+                        'unit': unit,
+                        'year': year,
+                        'value': float(value)
+                    }
+                    for nace, unit, value in zip(year_frame['nace_r2'], self._units(year_frame), year_frame['value'])
+                    if not pd.isna(nace) and not pd.isna(value) and value != 0  # Exclude zero values
+                )
+        
+        return flows
+
+    def get_period_flow_data(
+        self,
+        period: str,
+        include_imports: bool = True,
         include_exports: bool = True
     ) -> List[Dict[str, Union[str, float]]]:
-        """Get filtered trade flow data for specified year."""
+        """Get trade flows for a period label such as 2020, 2020Q1 or 2020M03.
+        
+        Only rows whose freq matches the period format are used, and a
+        period that does not match the frequency of a file raises a
+        SchemaMismatchError.
+        """
+        frequency = next(
+            (freq for freq, pattern in PERIOD_PATTERNS.items() if re.match(pattern, period)),
+            None
+        )
+        if frequency is None:
+            raise ValueError(f"Period {period} is not a year (YYYY), quarter (YYYYQn) or month (YYYYMmm)")
+        
         flows = []
+        for file_path, flow_type in self._flow_files(include_imports, include_exports):
+            df = self._read_and_clean_df(file_path)
+            result_df = self._split_metadata(df)
+            
+            file_frequencies = sorted(result_df['freq'].dropna().unique())
+            if frequency not in file_frequencies:
+                raise SchemaMismatchError(
+                    f"Period {period} has frequency {frequency} but {os.path.basename(file_path)} "
+                    f"contains {', '.join(file_frequencies)} data"
+                )
+            if period not in df.columns:
+                raise MissingYearError(f"Period {period} not found in {os.path.basename(file_path)}")
+            
+            result_df['value'] = df[period]
+            result_df = result_df[result_df['freq'] == frequency].dropna()
+            flows.extend(
+                {
+                    'source': row['nace_r2'],
+                    'target': flow_type,
+                    'unit': row.get('unit'),
+                    'value': float(row['value'])
+                }
+                for _, row in result_df.iterrows()
+                if row['value'] != 0  # Exclude zero values
+            )
         
-        if include_imports:
-            imports = self._process_flow_file(
-                self.imports_file, 
-                year, 
-                "Total Imports",
-                min_value,
-                drop_nan,
-                nace_level,
-                regions
-            )
-            flows.extend(imports)
+        return flows
+
+    def flows_frame(self, flows: List[Dict[str, Union[str, float]]]) -> pd.DataFrame:
+        """Convert flows into a frame with nace_r2, flow_type and value columns."""
+        return pd.DataFrame({
+            'nace_r2': [flow['source'] for flow in flows],
+            'flow_type': [flow['target'] for flow in flows],
+            'value': [flow['value'] for flow in flows]
+        })
+
+    def flow_matrix(
+        self, year: int, flow_type: str = IMPORTS_FLOW, unit_filter: Optional[str] = None
+    ) -> Tuple[List[str], List[str], List[List[float]]]:
+        """Get the values of a year as a dense geo by NACE grid.
+        
+        Returns (geos, nace_codes, values), both code lists sorted, where
+        values[i][j] is the flow of geos[i] and nace_codes[j]. Cells without
+        any reported value are NaN rather than 0. A file mixing units raises a
+        MixedUnitsError unless unit_filter keeps only one of them.
+        """
+        file_paths = dict((flow, path) for path, flow in self._flow_files())
+        if flow_type not in file_paths:
+            raise ValueError(f"Unknown flow type: {flow_type}; expected one of {', '.join(file_paths)}")
+        
+        df = self.get_year_frame(file_paths[flow_type], year)
+        df = self.single_unit(df, self._source_name(file_paths[flow_type]), unit_filter)
+        matrix = df.groupby(['geo', 'nace_r2'])['value'].sum(min_count=1).unstack('nace_r2')
+        matrix = matrix.sort_index().sort_index(axis=1).astype(float)
+        return list(matrix.index), list(matrix.columns), matrix.values.tolist()
+
+    def pivot_years(
+        self, geo: str, flow_type: str = IMPORTS_FLOW, unit_filter: Optional[str] = None
+    ) -> Tuple[List[int], List[Tuple[str, List[float]]]]:
+        """Get all years of one country as a NACE by year grid, e.g. for a heatmap.
+        
+        Returns (years, rows) with years sorted and one (nace, values) row per
+        sector in NACE order, where values[i] is the flow in years[i]. Cells
+        without any reported value are NaN rather than 0. A country mixing
+        units raises a MixedUnitsError unless unit_filter keeps only one of them.
+        """
+        file_paths = dict((flow, path) for path, flow in self._flow_files())
+        if flow_type not in file_paths:
+            raise ValueError(f"Unknown flow type: {flow_type}; expected one of {', '.join(file_paths)}")
+        
+        df = self.read_frame(file_paths[flow_type])
+        rows = df[df['geo'] == geo]
+        if rows.empty:
+            raise ValueError(f"Geo code {geo} not found in {self._source_name(file_paths[flow_type])}")
+        rows = self.single_unit(rows, self._source_name(file_paths[flow_type]), unit_filter)
+        
+        year_columns = sorted((col for col in df.columns if col.isdigit()), key=int)
+        grid = rows.groupby('nace_r2')[year_columns].sum(min_count=1).sort_index().astype(float)
+        return [int(year) for year in year_columns], [(nace, values.tolist()) for nace, values in grid.iterrows()]
+
+    def get_flow_data_map(
+        self, year: int, flow_type: str = IMPORTS_FLOW, on_duplicate: str = 'error', unit_filter: Optional[str] = None
+    ) -> Dict[str, float]:
+        """Get the values of a year as a dict from NACE code to value, e.g. for direct lookups.
+        
+        Rows of the same sector (across geos) are summed, while repeated series
+        are resolved by on_duplicate as in get_flow_data. A file mixing units
+        raises a MixedUnitsError unless unit_filter keeps only one of them.
+        NaN values are skipped; sectors without any reported value are absent.
+        """
+        file_paths = dict((flow, path) for path, flow in self._flow_files())
+        if flow_type not in file_paths:
+            raise ValueError(f"Unknown flow type: {flow_type}; expected one of {', '.join(file_paths)}")
+        
+        df = self._resolve_duplicates(self.get_year_frame(file_paths[flow_type], year), on_duplicate)
+        df = self.single_unit(df, self._source_name(file_paths[flow_type]), unit_filter)
+        totals = df.dropna(subset=['nace_r2', 'value']).groupby('nace_r2')['value'].sum()
+        return {nace: float(value) for nace, value in totals.items()}
+
+    def get_flow_data_arrow(self, year: int, **filters):
+        """Get the flows of a year as a pyarrow Table with nace_r2, flow_type and value columns.
+        
+        Table.to_pandas() gives a DataFrame without building per-flow dicts on
+        the caller's side. Extra keyword arguments are passed to get_flow_data
+        as filters.
+        """
+        import pyarrow as pa
+        
+        return pa.Table.from_pandas(self.flows_frame(self.get_flow_data(year, **filters)), preserve_index=False)
+
+    def get_flow_data_json(self, year: int, **filters) -> str:
+        """Get the flows of a year as a JSON array of {nace_r2, flow_type, value} objects.
+        
+        The string can be sent to a browser as is, without building per-flow
+        dicts first. Extra keyword arguments are passed to get_flow_data as
+        filters.
+        """
+        return self.flows_frame(self.get_flow_data(year, **filters)).to_json(orient='records')
+
+    def write_flows_parquet(self, year: int, out_path: str, **filters) -> int:
+        """Write the flows of a year to a Parquet file and return the row count.
+        
+        Extra keyword arguments are passed to get_flow_data as filters.
+        """
+        out_dir = os.path.dirname(os.path.abspath(out_path))
+        if not os.path.isdir(out_dir):
+            raise FlowFileNotFoundError(f"Output directory does not exist: {out_dir}")
+        
+        df = self.flows_frame(self.get_flow_data(year, **filters))
+        df.to_parquet(out_path, index=False)
+        return len(df)
+
+    def melt_to_parquet(self, file_path: Source, flow_type: str, out_path: str, chunksize: int = STREAM_CHUNK_ROWS) -> int:
+        """Write every year of a flow file in long form to Parquet and return the row count.
+        
+        Rows hold the metadata fields, flow_type, an integer year and the
+        value, like melt_flows with NaN values dropped. The file is parsed and
+        written chunksize rows at a time, so the long form is never held in
+        memory whole.
+        """
+        import pyarrow as pa
+        import pyarrow.parquet as pq
+        
+        out_dir = os.path.dirname(os.path.abspath(out_path))
+        if not os.path.isdir(out_dir):
+            raise FlowFileNotFoundError(f"Output directory does not exist: {out_dir}")
+        
+        schema = pa.schema(
+            [(field, pa.string()) for field in self.metadata_columns]
+            + [('flow_type', pa.string()), ('year', pa.int64()), ('value', pa.float64())]
+        )
+        rows = 0
+        with pq.ParquetWriter(out_path, schema) as writer:
+            for chunk in self._iter_clean_chunks(file_path, chunksize=chunksize):
+                metadata = self._split_metadata(chunk)
+                year_columns = [col for col in chunk.columns if col.isdigit()]
+                long_df = pd.concat([metadata, chunk[year_columns].astype(float)], axis=1).melt(
+                    id_vars=self.metadata_columns,
+                    value_vars=year_columns,
+                    var_name='year',
+                    value_name='value'
+                ).dropna(subset=['value'])
+                long_df['year'] = long_df['year'].astype(int)
+                long_df.insert(len(self.metadata_columns), 'flow_type', flow_type)
+                writer.write_table(pa.Table.from_pandas(long_df, schema=schema, preserve_index=False))
+                rows += len(long_df)
+        return rows
+
+    def write_flows(self, year: int, out_path: str, format: Optional[str] = None, **filters) -> int:
+        """Write the flows of a year to a CSV, JSON or Parquet file and return the row count.
+        
+        format defaults to the extension of out_path. JSON is written as a
+        list of records. Extra keyword arguments are passed to get_flow_data
+        as filters.
+        """
+        if format is None:
+            format = os.path.splitext(out_path)[1].lstrip('.').lower()
+        if format not in OUTPUT_FORMATS:
+            raise ValueError(f"Unsupported output format: {format or out_path}; expected one of {', '.join(OUTPUT_FORMATS)}")
+        if format == 'parquet':
+            return self.write_flows_parquet(year, out_path, **filters)
+        
+        out_dir = os.path.dirname(os.path.abspath(out_path))
+        if not os.path.isdir(out_dir):
+            raise FlowFileNotFoundError(f"Output directory does not exist: {out_dir}")
+        
+        df = self.flows_frame(self.get_flow_data(year, **filters))
+        if format == 'csv':
+            df.to_csv(out_path, index=False)
+        else:
+            df.to_json(out_path, orient='records')
+        return len(df)
+
+    def _read_glob_frame(self, pattern: str, on_file: Optional[Callable[[], None]] = None) -> pd.DataFrame:
+        """Read and concatenate every flow file matching a glob pattern.
+        
+        on_file is called after each file is read.
+        """
+        paths = sorted(glob.glob(pattern))
+        if not paths:
+            raise FlowFileNotFoundError(f"No files match pattern: {pattern}")
+        
+        frames = []
+        for path in paths:
+            frames.append(self.read_frame(path))
+            if on_file is not None:
+                on_file()
+        expected = set(frames[0].columns)
+        mismatched = [
+            f"{path} ({', '.join(frame.columns)})"
+            for path, frame in zip(paths, frames)
+            if set(frame.columns) != expected
+        ]
+        if mismatched:
+            raise SchemaMismatchError(
+                f"Files do not match the columns of {paths[0]} ({', '.join(frames[0].columns)}): "
+                f"{'; '.join(mismatched)}"
+            )
+        
+        return pd.concat(frames, ignore_index=True)
+
+    def get_flow_data_glob(
+        self,
+        imports_pattern: str,
+        exports_pattern: str,
+        year: int,
+        progress: Optional[ProgressCallback] = None
+    ) -> List[Dict[str, Union[str, float]]]:
+        """Get trade flows for a year from all files matching two glob patterns.
+        
+        Series repeated across files with the same nace_r2, geo and unit are summed.
+        progress is called with (completed, total) after each file is read, and
+        an exception it raises aborts the extraction.
+        """
+        patterns = [(imports_pattern, IMPORTS_FLOW), (exports_pattern, EXPORTS_FLOW)]
+        total = sum(len(glob.glob(pattern)) for pattern, _ in patterns)
+        completed = 0
+        def on_file() -> None:
+            nonlocal completed
+            completed += 1
+            if progress is not None:
+                progress(completed, total)
+        
+        year_str = str(year)
+        flows = []
+        for pattern, flow_type in patterns:
+            df = self._read_glob_frame(pattern, on_file)
+            if year_str not in df.columns:
+                raise MissingYearError(f"Year {year} not found in files matching {pattern}")
             
-        if include_exports:
-            exports = self._process_flow_file(
-                self.exports_file, 
-                year, 
-                "Total Exports",
-                min_value,
-                drop_nan,
-                nace_level,
-                regions
+            totals = df.dropna(subset=[year_str]).groupby(['nace_r2', 'geo', 'unit'])[year_str].sum()
+            flows.extend(
+                {
+                    'source': nace,
+                    'target': flow_type,
+                    'unit': unit,
+                    'value': float(value)
+                }
+                for (nace, geo, unit), value in totals.items()
+                if value != 0  # Exclude zero values
+            )
+        
+        return flows
+
+    def get_flow_data_from_bytes(
+        self,
+        imports_bytes: bytes,
+        exports_bytes: bytes,
+        year: int
+    ) -> List[Dict[str, Union[str, float]]]:
+        """Get trade flows for a year from in-memory file contents such as uploads.
+        
+        Parsing is identical to the path-based get_flow_data and uses the
+        separator and metadata settings of this parser.
+        """
+        flows = []
+        for data, flow_type in [(imports_bytes, IMPORTS_FLOW), (exports_bytes, EXPORTS_FLOW)]:
+            self._check_year_available(year, [data])
+            flows.extend(self._process_flow_file(data, year, flow_type))
+        return flows
+
+    def get_flow_data_single_file(
+        self,
+        file_path: Source,
+        indicator_column: str,
+        import_value: str,
+        export_value: str,
+        year: int
+    ) -> List[Dict[str, Union[str, float]]]:
+        """Get trade flows for a year from one file holding both imports and exports.
+        
+        Rows are split by their indicator_column metadata field (e.g. stk_flow
+        with 'IMP' and 'EXP'); a field missing from metadata_columns raises a
+        SchemaMismatchError and an indicator value without rows a ValueError.
+        Missing and zero values are excluded as in get_flow_data.
+        """
+        if indicator_column not in self.metadata_columns:
+            raise SchemaMismatchError(
+                f"No {indicator_column} field among the metadata columns: {', '.join(self.metadata_columns)}"
             )
-            flows.extend(exports)
         
+        df = self.get_year_frame(file_path, year)
+        flows = []
+        for indicator, flow_type in [(import_value, IMPORTS_FLOW), (export_value, EXPORTS_FLOW)]:
+            rows = df[df[indicator_column] == indicator]
+            if rows.empty:
+                raise ValueError(f"No rows with {indicator_column} {indicator} in {self._source_name(file_path)}")
+            
+            rows = self._resolve_duplicates(rows, 'error').dropna(subset=['nace_r2', 'value'])
+            flows.extend(
+                {
+                    'source': nace,
+                    'target': flow_type,
+                    'unit': unit,
+                    'value': self._scaled_value(value, 1.0)
+                }
+                for nace, unit, value in zip(rows['nace_r2'], self._units(rows), rows['value'])
+                if value != 0  # Exclude zero values
+            )
         return flows
+
+    def _download(self, url: str) -> bytes:
+        """Fetch the contents of a URL, reusing an earlier download of the same URL.
+        
+        Gzipped responses are returned as-is; the byte parser recognizes them.
+        """
+        with _DOWNLOAD_CACHE_LOCK:
+            cached = _DOWNLOAD_CACHE.get(url)
+        if cached is not None:
+            return cached
+        
+        request = urllib.request.Request(url, headers={'Accept-Encoding': 'gzip'})
+        try:
+            with urllib.request.urlopen(request, timeout=DOWNLOAD_TIMEOUT) as response:
+                if response.status != 200:
+                    raise FlowDownloadError(f"Could not download {url}: HTTP status {response.status}")
+                data = response.read()
+        except urllib.error.HTTPError as e:
+            raise FlowDownloadError(f"Could not download {url}: HTTP status {e.code}") from e
+        except urllib.error.URLError as e:
+            raise FlowDownloadError(f"Could not download {url}: {e.reason}") from e
+        
+        with _DOWNLOAD_CACHE_LOCK:
+            _DOWNLOAD_CACHE[url] = data
+        return data
+
+    def get_flow_data_url(self, imports_url: str, exports_url: str, year: int) -> List[Dict[str, Union[str, float]]]:
+        """Get trade flows for a year from files served over HTTP(S), e.g. the Eurostat bulk download.
+        
+        Downloads are cached per URL for the lifetime of the process, so
+        switching years does not fetch the files again.
+        """
+        return self.get_flow_data_from_bytes(self._download(imports_url), self._download(exports_url), year)
+
+    def flag_histogram(self, file_path: Source) -> List[Tuple[str, int]]:
+        """Count the Eurostat flags of all period cells in a file, most frequent first.
+        
+        Combined flags such as 'bp' count once per letter, and cells with the
+        ':' marker (or another of null_values) count as 'missing'.
+        """
+        df = self._read_raw_df(file_path)
+        counts: Dict[str, int] = {}
+        for col in df.columns:
+            if not self._is_period_column(col):
+                continue
+            
+            parts = df[col].dropna().astype(str).str.extract(VALUE_PATTERN)
+            missing = int(parts['number'].isin(self.null_values).sum())
+            if missing:
+                counts['missing'] = counts.get('missing', 0) + missing
+            for flags in parts['flags'].dropna():
+                for flag in flags:
+                    counts[flag] = counts.get(flag, 0) + 1
+        
+        return sorted(counts.items(), key=lambda item: (-item[1], item[0]))
+
+    def get_quality_report(self, year: int) -> Dict[str, int]:
+        """Count how much of a year's data across both files is missing or suspicious.
+        
+        The report holds the total rows, NaN values skipped by get_flow_data,
+        cells carrying the ':' missing marker (or another of null_values),
+        negative values, the number of distinct geos, rows whose field count
+        differs from the header (left out when skip_bad_rows is set) and rows
+        with the wrong number of metadata fields.
+        """
+        self._check_year_available(year, [file_path for file_path, _ in self._flow_files()])
+        
+        year_str = str(year)
+        report = {
+            'total_rows': 0, 'nan_skipped': 0, 'missing_markers': 0, 'negative_values': 0, 'malformed_rows': 0,
+            'metadata_mismatches': 0
+        }
+        geos = set()
+        for file_path, _ in self._flow_files():
+            raw_values = self._read_raw_df(file_path)[year_str].astype(str).str.strip()
+            result_df = self.get_year_frame(file_path, year)
+            
+            report['total_rows'] += len(result_df)
+            report['nan_skipped'] += int(result_df['value'].isna().sum())
+            report['missing_markers'] += int(raw_values.str.extract(VALUE_PATTERN)['number'].isin(self.null_values).sum())
+            report['negative_values'] += int((result_df['value'] < 0).sum())
+            geos.update(result_df['geo'].dropna())
+            report['malformed_rows'] += self._malformed_rows(file_path)
+            report['metadata_mismatches'] += len(self.metadata_mismatch_rows(file_path))
+        
+        report['distinct_geos'] = len(geos)
+        return report
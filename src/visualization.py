@@ -1 +1,28 @@
 # Graphical methods (Plotly, NetworkX)
+import os
+from typing import List, Optional, Tuple
+from src.parser import FIGAROParser, exact_sum
+
+def sankey_edges(parser: FIGAROParser, year: int, unit_filter: Optional[str] = None) -> List[Tuple[str, str, float]]:
+    """Get country-sector Sankey edges as (source, target, value).
+    
+    Imports flow from geo to nace_r2 and exports from nace_r2 to geo. Duplicate
+    pairs are summed, and NaN or non-positive edges are dropped since Sankey
+    links need a positive width. A file mixing units raises a MixedUnitsError
+    unless unit_filter keeps only one of them.
+    """
+    edges = []
+    for file_path, source, target in [
+        (parser.imports_file, 'geo', 'nace_r2'),
+        (parser.exports_file, 'nace_r2', 'geo')
+    ]:
+        df = parser.get_year_frame(file_path, year).dropna(subset=['value'])
+        df = parser.single_unit(df, os.path.basename(file_path), unit_filter)
+        totals = df.groupby([source, target])['value'].agg(exact_sum)
+        edges.extend(
+            (src, tgt, float(value))
+            for (src, tgt), value in totals.items()
+            if value > 0
+        )
+    
+    return edges
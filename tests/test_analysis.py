@@ -0,0 +1,608 @@
+import math
+import os
+import pytest
+import tempfile
+from src.errors import MissingYearError, MixedUnitsError
+from src.parser import FIGAROParser
+from src import analysis
+from src.geo_names import geo_name
+from tests.test_parser import write_figaro_files
+
+@pytest.fixture
+def trade_parser():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        import_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t2020\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t100\t110\n"
+            "A,B01,EXP_GO,MIO_EUR,BE\t50\t60\n"
+            "A,B02,EXP_GO,MIO_EUR,AT\t30\t:\n"
+            "A,B04,EXP_GO,MIO_EUR,AT\t:\t:\n"
+        )
+        export_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t2020\n"
+            "A,B01,IMP_GO,MIO_EUR,AT\t200\t220\n"
+            "A,B02,IMP_GO,MIO_EUR,AT\t:\t10\n"
+            "A,B03,IMP_GO,MIO_EUR,AT\t40\t50\n"
+            "A,B04,IMP_GO,MIO_EUR,AT\t:\t:\n"
+        )
+        write_figaro_files(tmpdir, import_content, export_content)
+        yield FIGAROParser(tmpdir)
+
+def test_compare_datasets(trade_parser):
+    with tempfile.TemporaryDirectory() as tmpdir:
+        revised = os.path.join(tmpdir, 'revised.tsv')
+        with open(revised, 'w') as f:
+            f.write(
+                "freq,nace_r2,c_exp,unit,geo\t2019\n"
+                "A,B01,EXP_GO,MIO_EUR,AT\t120\n"
+                "A,B01,EXP_GO,MIO_EUR,BE\t50\n"
+                "A,B05,EXP_GO,MIO_EUR,AT\t8\n"
+            )
+        
+        compared = analysis.compare_datasets(trade_parser, trade_parser.imports_file, revised, 2019)
+    
+    assert [nace for nace, _, _, _ in compared] == ['B01', 'B02', 'B05']
+    assert compared[0] == ('B01', 150.0, 170.0, 20.0)
+    assert compared[1][1] == 30.0 and math.isnan(compared[1][2]) and math.isnan(compared[1][3])
+    assert math.isnan(compared[2][1]) and compared[2][2] == 8.0
+
+def test_net_flows(trade_parser):
+    balance = analysis.net_flows(trade_parser, 2019)
+    # Duplicate B01 imports are summed, one-sided sectors count the other side as zero
+    assert balance == [('B01', 50.0), ('B03', 40.0), ('B02', -30.0)]
+    assert analysis.net_flows(trade_parser, 2019, inner_only=True) == [('B01', 50.0)]
+    assert analysis.net_flows(trade_parser, 2019, absolute=True) == [('B01', 50.0, 1), ('B03', 40.0, 1), ('B02', 30.0, -1)]
+
+def test_flows_per_gdp(trade_parser, caplog):
+    with caplog.at_level('WARNING', logger='src.analysis'):
+        shares = analysis.flows_per_gdp(trade_parser, 2019, {'AT': 400.0})
+    
+    assert shares == [('AT', 'Total Exports', 60.0), ('AT', 'Total Imports', 32.5)]
+    assert "BE" in caplog.text
+
+def test_flows_per_capita(trade_parser, caplog):
+    with caplog.at_level('WARNING', logger='src.analysis'):
+        per_capita = analysis.flows_per_capita(trade_parser, 2019, {'AT': 10.0})
+    
+    assert per_capita == [('AT', 'Total Exports', 24.0), ('AT', 'Total Imports', 13.0)]
+    assert "BE" in caplog.text
+    with pytest.raises(ValueError, match="positive"):
+        analysis.flows_per_capita(trade_parser, 2019, {'AT': 0.0})
+
+def test_paired_flows(trade_parser):
+    paired = analysis.paired_flows(trade_parser, 2019)
+    assert [nace for nace, _, _ in paired] == ['B01', 'B03', 'B02']
+    assert paired[0] == ('B01', 150.0, 200.0)
+    assert math.isnan(paired[1][1]) and paired[1][2] == 40.0
+    assert paired[2][1] == 30.0 and math.isnan(paired[2][2])
+    
+    assert analysis.paired_flows(trade_parser, 2019, fill_zero=True) == [
+        ('B01', 150.0, 200.0),
+        ('B03', 0.0, 40.0),
+        ('B02', 30.0, 0.0)
+    ]
+    assert analysis.paired_flows(trade_parser, 2019, inner_only=True) == [('B01', 150.0, 200.0)]
+
+def test_full_sector_breakdown(trade_parser):
+    breakdown = analysis.full_sector_breakdown(trade_parser, 2019)
+    
+    assert [nace for nace, _, _, _ in breakdown] == ['B01', 'B03', 'B02']
+    assert breakdown[0] == ('B01', 150.0, 200.0, 50.0)
+    assert math.isnan(breakdown[1][1]) and breakdown[1][2:] == (40.0, 40.0)
+    assert breakdown[2][1] == 30.0 and math.isnan(breakdown[2][2]) and breakdown[2][3] == -30.0
+
+def test_flows_by_geo(trade_parser):
+    assert analysis.flows_by_geo(trade_parser, 2019) == [
+        ('AT', 'Total Exports', 240.0),
+        ('AT', 'Total Imports', 130.0),
+        ('BE', 'Total Imports', 50.0)
+    ]
+    assert analysis.flows_by_geo(trade_parser, 2019, geos=['BE', 'XX']) == [('BE', 'Total Imports', 50.0)]
+    assert analysis.flows_by_geo(trade_parser, 2019, geos=[]) == []
+
+def test_flows_by_geo_with_names(trade_parser):
+    assert analysis.flows_by_geo(trade_parser, 2019, with_geo_names=True) == [
+        ('AT', 'Total Exports', 240.0, 'Austria'),
+        ('AT', 'Total Imports', 130.0, 'Austria'),
+        ('BE', 'Total Imports', 50.0, 'Belgium')
+    ]
+
+def test_geo_name_special_cases():
+    assert geo_name('EL') == 'Greece'
+    assert geo_name('UK') == 'United Kingdom'
+    assert geo_name('XX') == 'XX'
+
+def test_balance_by_geo(trade_parser):
+    # BE only appears in the imports file
+    assert analysis.balance_by_geo(trade_parser, 2019) == [('BE', -50.0), ('AT', 110.0)]
+    assert analysis.balance_by_geo(trade_parser, 2019, geos={'AT', 'XX'}) == [('AT', 110.0)]
+
+def test_coverage_ratio_by_geo(trade_parser):
+    # BE has imports but no exports
+    assert analysis.coverage_ratio_by_geo(trade_parser, 2019) == [('AT', pytest.approx(240 / 130 * 100)), ('BE', 0.0)]
+    
+    with tempfile.TemporaryDirectory() as tmpdir:
+        import_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t40\n"
+            "A,B01,EXP_GO,MIO_EUR,DE\t0\n"
+        )
+        export_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,IMP_GO,MIO_EUR,AT\t10\n"
+            "A,B01,IMP_GO,MIO_EUR,DE\t5\n"
+            "A,B01,IMP_GO,MIO_EUR,FR\t8\n"
+        )
+        write_figaro_files(tmpdir, import_content, export_content)
+        # DE has zero and FR no imports, so both are skipped
+        assert analysis.coverage_ratio_by_geo(FIGAROParser(tmpdir), 2019) == [('AT', 25.0)]
+
+def test_mixed_units_guard():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        import_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t2020\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t100\t120\n"
+            "A,B01,EXP_GO,THS_T,AT\t7\t8\n"
+        )
+        export_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t2020\n"
+            "A,B01,IMP_GO,MIO_EUR,AT\t50\t60\n"
+        )
+        write_figaro_files(tmpdir, import_content, export_content)
+        parser = FIGAROParser(tmpdir)
+        
+        with pytest.raises(MixedUnitsError, match="mixes units MIO_EUR, THS_T"):
+            analysis.flows_by_geo(parser, 2019)
+        with pytest.raises(MixedUnitsError):
+            analysis.net_flows(parser, 2019)
+        
+        assert analysis.flows_by_geo(parser, 2019, unit_filter='MIO_EUR') == [
+            ('AT', 'Total Exports', 50.0),
+            ('AT', 'Total Imports', 100.0)
+        ]
+        assert analysis.net_flows(parser, 2019, unit_filter='MIO_EUR') == [('B01', -50.0)]
+        
+        # Every aggregation goes through the same guard and accepts unit_filter
+        guarded = [
+            (lambda unit_filter: analysis.growth_flows(parser, 2020, 2019, unit_filter=unit_filter),
+             [('B01', 'Total Imports', 20.0), ('B01', 'Total Exports', 20.0)]),
+            (lambda unit_filter: analysis.flow_shares(parser, 2019, unit_filter=unit_filter),
+             [('B01', 'Total Imports', 100.0), ('B01', 'Total Exports', 100.0)]),
+            (lambda unit_filter: analysis.rank_sectors(parser, 2019, 'Total Imports', unit_filter=unit_filter),
+             [(1, 'B01', 100.0)]),
+            (lambda unit_filter: analysis.top_partners(parser, 'Total Imports', 'B01', 2019, unit_filter=unit_filter),
+             [('EXP_GO', 100.0)]),
+            (lambda unit_filter: analysis.aggregate_nace_level(parser, 2019, 1, unit_filter=unit_filter),
+             [('B', 'Total Imports', 100.0), ('B', 'Total Exports', 50.0)]),
+            (lambda unit_filter: parser.get_flow_data_map(2019, unit_filter=unit_filter), {'B01': 100.0}),
+            (lambda unit_filter: parser.flow_matrix(2019, unit_filter=unit_filter), (['AT'], ['B01'], [[100.0]])),
+            (lambda unit_filter: analysis.sector_time_series(parser, 'B01', 'AT', unit_filter=unit_filter),
+             [(2019, 'Total Exports', 50.0), (2019, 'Total Imports', 100.0),
+              (2020, 'Total Exports', 60.0), (2020, 'Total Imports', 120.0)]),
+            (lambda unit_filter: analysis.balance_time_series(parser, 'B01', 'AT', unit_filter=unit_filter),
+             [(2019, -50.0), (2020, -60.0)]),
+            (lambda unit_filter: analysis.full_sector_breakdown(parser, 2019, unit_filter=unit_filter),
+             [('B01', 100.0, 50.0, -50.0)]),
+            (lambda unit_filter: analysis.flows_per_gdp(parser, 2019, {'AT': 1000.0}, unit_filter=unit_filter),
+             [('AT', 'Total Exports', 5.0), ('AT', 'Total Imports', 10.0)]),
+            (lambda unit_filter: analysis.flows_per_capita(parser, 2019, {'AT': 10.0}, unit_filter=unit_filter),
+             [('AT', 'Total Exports', 5.0), ('AT', 'Total Imports', 10.0)]),
+            (lambda unit_filter: analysis.compare_datasets(
+                parser, parser.imports_file, parser.exports_file, 2019, unit_filter=unit_filter
+            ), [('B01', 100.0, 50.0, -50.0)])
+        ]
+        for aggregate, expected in guarded:
+            with pytest.raises(MixedUnitsError):
+                aggregate(None)
+            assert aggregate('MIO_EUR') == expected
+
+def test_mixed_units_across_files():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        import_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t100\n"
+        )
+        export_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,IMP_GO,THS_T,AT\t7\n"
+        )
+        write_figaro_files(tmpdir, import_content, export_content)
+        parser = FIGAROParser(tmpdir)
+        
+        # Each file holds one unit, but their totals cannot be set against each other
+        assert len(analysis.flows_by_geo(parser, 2019)) == 2
+        with pytest.raises(MixedUnitsError, match="estat_naio_10_fgti.tsv with estat_naio_10_fgte.tsv mixes units MIO_EUR, THS_T"):
+            analysis.net_flows(parser, 2019)
+        for joined in [analysis.paired_flows, analysis.balance_by_geo, analysis.coverage_ratio_by_geo]:
+            with pytest.raises(MixedUnitsError):
+                joined(parser, 2019)
+        
+        assert analysis.net_flows(parser, 2019, unit_filter='MIO_EUR') == [('B01', -100.0)]
+
+def test_compare_geos(trade_parser):
+    compared = analysis.compare_geos(trade_parser, 'Total Imports', 'AT', 'BE', 2019)
+    # B02 is only reported for AT and B04 has no value for either
+    assert [nace for nace, _, _ in compared] == ['B01', 'B02']
+    assert compared[0] == ('B01', 100.0, 50.0)
+    assert compared[1][1] == 30.0 and math.isnan(compared[1][2])
+    
+    with pytest.raises(ValueError):
+        analysis.compare_geos(trade_parser, 'Total Trade', 'AT', 'BE', 2019)
+
+def test_exact_totals():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t1e16\n"
+            "A,B01,EXP_GO,MIO_EUR,BE\t1.5\n"
+            "A,B01,EXP_GO,MIO_EUR,DE\t-1e16\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        
+        # Accumulating floats rounds 1e16 + 1.5 and loses the small value
+        assert 1e16 + 1.5 - 1e16 != 1.5
+        assert analysis.net_flows(parser, 2019) == [('B01', 0.0)]
+        assert analysis.paired_flows(parser, 2019) == [('B01', 1.5, 1.5)]
+        
+        # Whole numbers are added as ints, beyond the 2**53 floats hold exactly
+        whole_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B02,EXP_GO,MIO_EUR,AT\t9007199254740993\n"
+            "A,B02,EXP_GO,MIO_EUR,BE\t-9007199254740992\n"
+        )
+        whole_dir = os.path.join(tmpdir, 'whole')
+        write_figaro_files(whole_dir, whole_content, whole_content)
+        whole = FIGAROParser(whole_dir)
+        
+        assert math.fsum([9007199254740993, -9007199254740992]) == 0.0
+        assert analysis.paired_flows(whole, 2019) == [('B02', 1.0, 1.0)]
+        assert analysis.sector_trade_share(whole, 2019) == [('B02', 100.0)]
+
+def test_flows_by_partner_group():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,DE,MIO_EUR,AT\t100\n"
+            "A,B02,FR,MIO_EUR,AT\t50\n"
+            "A,B01,US,MIO_EUR,AT\t30\n"
+            "A,B01,EL,MIO_EUR,AT\t:\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t5\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        groups = analysis.flows_by_partner_group(FIGAROParser(tmpdir), 2019)
+    
+    assert groups == [
+        ('intra_eu', 'Total Imports', 150.0),
+        ('extra_eu', 'Total Imports', 30.0),
+        ('other', 'Total Imports', 5.0),
+        ('intra_eu', 'Total Exports', 150.0),
+        ('extra_eu', 'Total Exports', 30.0),
+        ('other', 'Total Exports', 5.0)
+    ]
+
+def test_top_partners_and_shares():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,DE,MIO_EUR,AT\t100\n"
+            "A,B01,DE,MIO_EUR,BE\t40\n"
+            "A,B02,FR,MIO_EUR,AT\t50\n"
+            "A,B01,US,MIO_EUR,AT\t30\n"
+            "A,B01,EL,MIO_EUR,AT\t:\n"
+            "A,B01,FR,MIO_EUR,AT\t10\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        
+        assert analysis.top_partners(parser, 'Total Exports', 'B01', 2019, top_n=2) == [('DE', 140.0), ('US', 30.0)]
+        assert analysis.top_partners(parser, 'Total Imports', 'B01', 2019) == [('DE', 140.0), ('US', 30.0), ('FR', 10.0)]
+        with pytest.raises(ValueError, match="NACE B09 not found"):
+            analysis.top_partners(parser, 'Total Exports', 'B09', 2019)
+        
+        shares = analysis.partner_shares(parser, 'Total Exports', 'B01', 2019)
+        assert [partner for partner, _ in shares] == ['DE', 'US', 'FR']
+        assert [share for _, share in shares] == pytest.approx([140 / 180 * 100, 30 / 180 * 100, 10 / 180 * 100])
+
+def test_aggregate_geos(trade_parser, caplog):
+    with caplog.at_level('WARNING', logger='src.analysis'):
+        totals = analysis.aggregate_geos(trade_parser, 2019, ['AT', 'BE', 'LU'], 'Benelux+AT')
+    
+    assert totals == [('B01', 'Benelux+AT', 150.0), ('B02', 'Benelux+AT', 30.0)]
+    assert "LU" in caplog.text
+    
+    assert analysis.aggregate_geos(trade_parser, 2019, ['AT'], 'AT', flow_type='Total Exports') == [
+        ('B01', 'AT', 200.0),
+        ('B03', 'AT', 40.0)
+    ]
+    with pytest.raises(ValueError):
+        analysis.aggregate_geos(trade_parser, 2019, ['AT'], 'AT', flow_type='Total Transit')
+
+def test_growth_flows(trade_parser):
+    growth = analysis.growth_flows(trade_parser, 2020, 2019)
+    # B02 lacks one of the years in both files and is skipped
+    assert [(nace, flow_type) for nace, flow_type, _ in growth] == [
+        ('B01', 'Total Imports'),
+        ('B01', 'Total Exports'),
+        ('B03', 'Total Exports')
+    ]
+    assert [value for _, _, value in growth] == pytest.approx([20 / 150 * 100, 10.0, 25.0])
+
+def test_diff_flows(trade_parser):
+    assert analysis.diff_flows(trade_parser, 2020, 2019) == [
+        ('B01', 'Total Imports', 20.0),
+        ('B01', 'Total Exports', 20.0),
+        ('B03', 'Total Exports', 10.0)
+    ]
+    assert analysis.diff_flows(trade_parser, 2019, 2020, absolute=True) == [
+        ('B01', 'Total Imports', 20.0, -1),
+        ('B01', 'Total Exports', 20.0, -1),
+        ('B03', 'Total Exports', 10.0, -1)
+    ]
+
+def test_cagr_flows(trade_parser):
+    cagr = analysis.cagr_flows(trade_parser, 2019, 2020)
+    # Over a single year CAGR equals the plain growth rate
+    assert cagr == [
+        (nace, flow_type, pytest.approx(value))
+        for nace, flow_type, value in analysis.growth_flows(trade_parser, 2020, 2019)
+    ]
+    
+    with pytest.raises(ValueError):
+        analysis.cagr_flows(trade_parser, 2020, 2019)
+
+def test_aggregate_nace_level(trade_parser):
+    assert analysis.aggregate_nace_level(trade_parser, 2019, 2) == [
+        ('B0', 'Total Imports', 180.0),
+        ('B0', 'Total Exports', 240.0)
+    ]
+    
+    with pytest.raises(ValueError):
+        analysis.aggregate_nace_level(trade_parser, 2019, 0)
+
+def test_aggregate_nace_growth(trade_parser):
+    unweighted = analysis.aggregate_nace_growth(trade_parser, 2020, 2019, 2)
+    weighted = analysis.aggregate_nace_growth(trade_parser, 2020, 2019, 2, volume_weighted=True)
+    
+    # Imports only have B01 so both agree; exports average B01 (10%) and B03 (25%)
+    assert [(nace, flow_type) for nace, flow_type, _ in weighted] == [
+        ('B0', 'Total Imports'), ('B0', 'Total Exports')
+    ]
+    assert [value for _, _, value in unweighted] == pytest.approx([20 / 150 * 100, 17.5])
+    assert [value for _, _, value in weighted] == pytest.approx([20 / 150 * 100, (200 * 10 + 40 * 25) / 240])
+
+def test_trade_ratio(trade_parser):
+    # B02 and B03 only have one side in 2019 and are excluded
+    assert analysis.trade_ratio(trade_parser, 2019) == [('B01', pytest.approx(200 / 150))]
+
+def test_flow_shares(trade_parser):
+    shares = analysis.flow_shares(trade_parser, 2019)
+    assert [(nace, flow_type) for nace, flow_type, _ in shares] == [
+        ('B01', 'Total Imports'),
+        ('B02', 'Total Imports'),
+        ('B01', 'Total Exports'),
+        ('B03', 'Total Exports')
+    ]
+    assert [value for _, _, value in shares] == pytest.approx([150 / 180 * 100, 30 / 180 * 100, 200 / 240 * 100, 40 / 240 * 100])
+
+def test_sector_trade_share(trade_parser):
+    shares = analysis.sector_trade_share(trade_parser, 2019)
+    assert [nace for nace, _ in shares] == ['B01', 'B03', 'B02']
+    assert [value for _, value in shares] == pytest.approx([350 / 420 * 100, 40 / 420 * 100, 30 / 420 * 100])
+    assert analysis.sector_trade_share(trade_parser, 2019, top_n=1) == [('B01', pytest.approx(350 / 420 * 100))]
+
+def test_concentration_index(trade_parser):
+    imports_hhi, exports_hhi = analysis.concentration_index(trade_parser, 2019)
+    assert imports_hhi == pytest.approx((150 / 180) ** 2 + (30 / 180) ** 2)
+    assert exports_hhi == pytest.approx((200 / 240) ** 2 + (40 / 240) ** 2)
+    
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = "freq,nace_r2,c_exp,unit,geo\t2019\nA,B01,EXP_GO,MIO_EUR,AT\t0\n"
+        write_figaro_files(tmpdir, content, content)
+        assert all(math.isnan(index) for index in analysis.concentration_index(FIGAROParser(tmpdir), 2019))
+
+def test_rank_sectors():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B04,EXP_GO,MIO_EUR,AT\t50\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t80\n"
+            "A,B03,EXP_GO,MIO_EUR,AT\t50\n"
+            "A,B02,EXP_GO,MIO_EUR,AT\t:\n"
+            "A,B05,EXP_GO,MIO_EUR,AT\t10\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        ranked = analysis.rank_sectors(FIGAROParser(tmpdir), 2019, 'Total Exports')
+    
+    assert ranked == [(1, 'B01', 80.0), (2, 'B03', 50.0), (2, 'B04', 50.0), (4, 'B05', 10.0)]
+
+def test_shares_other_bucket(trade_parser):
+    # B02 (7.1%) and B03 (9.5%) fall below 10% and are merged
+    assert analysis.sector_trade_share(trade_parser, 2019, other_threshold=10.0) == [
+        ('B01', pytest.approx(350 / 420 * 100)),
+        ('Other', pytest.approx(70 / 420 * 100))
+    ]
+    # Only one sector per flow type is small, so nothing is merged
+    assert analysis.flow_shares(trade_parser, 2019, other_threshold=20.0) == analysis.flow_shares(trade_parser, 2019)
+    assert analysis.sector_trade_share(trade_parser, 2019, other_threshold=30 / 420 * 100) == (
+        analysis.sector_trade_share(trade_parser, 2019)
+    )
+
+def test_deflate_flows(trade_parser):
+    flows = analysis.deflate_flows(trade_parser, 2019, 1.25, geo='AT')
+    assert [f['value'] for f in flows] == [80.0, 24.0, 160.0, 32.0]
+    assert [f['value'] for f in analysis.deflate_flows(trade_parser, 2019, 1.25, geo='AT', scale=10.0)] == [
+        8.0, 2.4, 16.0, 3.2
+    ]
+    
+    with pytest.raises(ValueError, match="positive"):
+        analysis.deflate_flows(trade_parser, 2019, 0.0)
+
+def test_trade_openness(trade_parser):
+    assert analysis.trade_openness(trade_parser, 2019, 840.0) == 0.5
+    with pytest.raises(ValueError, match="zero"):
+        analysis.trade_openness(trade_parser, 2019, 0)
+
+def test_flow_summary(trade_parser):
+    assert analysis.flow_summary(trade_parser, 2019) == {
+        'Total Imports': {'min': 30.0, 'max': 100.0, 'mean': 60.0, 'median': 50.0, 'p25': 40.0, 'p75': 75.0},
+        'Total Exports': {'min': 40.0, 'max': 200.0, 'mean': 120.0, 'median': 120.0, 'p25': 80.0, 'p75': 160.0}
+    }
+
+def test_classify_sectors(trade_parser):
+    assert analysis.classify_sectors(trade_parser, 2019) == [
+        ('B01', 'net_exporter', 50.0),
+        ('B03', 'net_exporter', 40.0),
+        ('B02', 'net_importer', -30.0)
+    ]
+    labels = [label for _, label, _ in analysis.classify_sectors(trade_parser, 2019, tolerance=45.0)]
+    assert labels == ['net_exporter', 'balanced', 'balanced']
+
+def test_sector_time_series(trade_parser):
+    assert analysis.sector_time_series(trade_parser, 'B02', 'AT') == [
+        (2019, 'Total Imports', 30.0),
+        (2020, 'Total Exports', 10.0)
+    ]
+    
+    with pytest.raises(ValueError):
+        analysis.sector_time_series(trade_parser, 'B02', 'BE')
+
+def test_balance_time_series():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        import_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2017\t2018\t2019\t2020\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t10\t:\t:\t30\n"
+            "A,B02,EXP_GO,MIO_EUR,AT\t5\t:\t:\t:\n"
+        )
+        export_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2017\t2018\t2019\t2020\n"
+            "A,B01,IMP_GO,MIO_EUR,AT\t15\t:\t20\t25\n"
+        )
+        write_figaro_files(tmpdir, import_content, export_content)
+        parser = FIGAROParser(tmpdir)
+        
+        # 2018 has neither side and is skipped, 2019 counts the missing imports as zero
+        assert analysis.balance_time_series(parser, 'B01', 'AT') == [(2017, 5.0), (2019, 20.0), (2020, -5.0)]
+        # B02 has no exports at all
+        assert analysis.balance_time_series(parser, 'B02', 'AT') == [(2017, -5.0)]
+
+def test_import_export_correlation():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        import_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2017\t2018\t2019\t2020\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t10\t20\t30\t40\n"
+            "A,B02,EXP_GO,MIO_EUR,AT\t10\t20\t:\t:\n"
+        )
+        export_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2017\t2018\t2019\t2020\n"
+            "A,B01,IMP_GO,MIO_EUR,AT\t21\t:\t61\t81\n"
+            "A,B02,IMP_GO,MIO_EUR,AT\t5\t7\t9\t11\n"
+        )
+        write_figaro_files(tmpdir, import_content, export_content)
+        parser = FIGAROParser(tmpdir)
+        
+        assert analysis.import_export_correlation(parser, 'B01', 'AT') == pytest.approx(1.0)
+        assert math.isnan(analysis.import_export_correlation(parser, 'B02', 'AT'))  # Two overlapping years
+
+def test_cumulative_time_series():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        import_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2017\t2018\t2019\t2020\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t:\t10\t:\t5\n"
+        )
+        export_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2017\t2018\t2019\t2020\n"
+            "A,B01,IMP_GO,MIO_EUR,AT\t1\t2\t3\t4\n"
+        )
+        write_figaro_files(tmpdir, import_content, export_content)
+        series = analysis.cumulative_time_series(FIGAROParser(tmpdir), 'B01', 'AT')
+    
+    assert [point for point in series if point[1] == 'Total Imports'] == [
+        (2018, 'Total Imports', 10.0),
+        (2019, 'Total Imports', 10.0),
+        (2020, 'Total Imports', 15.0)
+    ]
+    assert [value for _, flow_type, value in series if flow_type == 'Total Exports'] == [1.0, 3.0, 6.0, 10.0]
+
+def test_indexed_time_series(trade_parser):
+    series = analysis.indexed_time_series(trade_parser, 'B01', 'AT', 2019)
+    assert [(year, flow_type) for year, flow_type, _ in series] == [
+        (2019, 'Total Exports'), (2019, 'Total Imports'), (2020, 'Total Exports'), (2020, 'Total Imports')
+    ]
+    assert [value for _, _, value in series] == pytest.approx([100.0, 100.0, 110.0, 110.0])
+    
+    # B02 imports have no 2020 value and B02 exports no 2019 value
+    with pytest.raises(ValueError, match="base year 2020"):
+        analysis.indexed_time_series(trade_parser, 'B02', 'AT', 2020)
+    with pytest.raises(MissingYearError):
+        analysis.indexed_time_series(trade_parser, 'B01', 'AT', 2018)
+
+def test_sector_time_series_interpolated():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        import_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2017\t2018\t2019\t2020\t2021\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t:\t10\t:\t30\t:\n"
+        )
+        export_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2017\t2018\t2019\t2020\t2021\n"
+            "A,B01,IMP_GO,MIO_EUR,AT\t:\t:\t5\t:\t:\n"
+        )
+        write_figaro_files(tmpdir, import_content, export_content)
+        parser = FIGAROParser(tmpdir)
+        
+        assert analysis.sector_time_series_interpolated(parser, 'B01', 'AT') == [
+            (2018, 'Total Imports', 10.0, False),
+            (2019, 'Total Exports', 5.0, False),
+            (2019, 'Total Imports', 20.0, True),
+            (2020, 'Total Imports', 30.0, False)
+        ]
+
+def test_smoothed_time_series(trade_parser):
+    shrunk = analysis.smoothed_time_series(trade_parser, 'B01', 'AT', 3)
+    assert shrunk == [
+        (2019, 'Total Exports', 210.0),
+        (2019, 'Total Imports', 105.0),
+        (2020, 'Total Exports', 210.0),
+        (2020, 'Total Imports', 105.0)
+    ]
+    
+    padded = analysis.smoothed_time_series(trade_parser, 'B01', 'AT', 3, edges='nan')
+    assert all(math.isnan(value) for _, _, value in padded)
+    
+    assert analysis.smoothed_time_series(trade_parser, 'B01', 'AT', 1) == (
+        analysis.sector_time_series(trade_parser, 'B01', 'AT')
+    )
+    
+    for window in (0, 2, -1, 1.5):
+        with pytest.raises(ValueError):
+            analysis.smoothed_time_series(trade_parser, 'B01', 'AT', window)
+    with pytest.raises(ValueError):
+        analysis.smoothed_time_series(trade_parser, 'B01', 'AT', 3, edges='wrap')
+
+def test_seasonal_decompose():
+    pattern = [2.0, -1.0, -2.0, 1.0]
+    labels = [f"{year}Q{quarter}" for year in (2018, 2019, 2020) for quarter in range(1, 5)]
+    values = [10 + i + pattern[i % 4] for i in range(len(labels))]
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t" + "\t".join(labels) + "\n"
+            "Q,B01,EXP_GO,MIO_EUR,AT\t" + "\t".join(str(v) for v in values) + "\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        
+        points = analysis.seasonal_decompose(parser, 'B01', 'AT', 4)
+        assert [label for label, _, _, _ in points] == labels
+        assert [observed for _, observed, _, _ in points] == values
+        assert [seasonal for _, _, _, seasonal in points] == pytest.approx(pattern * 3)
+        
+        trend = [trend for _, _, trend, _ in points]
+        assert all(math.isnan(t) for t in trend[:2] + trend[-2:])
+        assert trend[2:-2] == pytest.approx([10 + i for i in range(2, len(labels) - 2)])
+        
+        # Twelve quarters are less than two seasons of eight
+        short = analysis.seasonal_decompose(parser, 'B01', 'AT', 8)
+        assert all(math.isnan(trend) and math.isnan(seasonal) for _, _, trend, seasonal in short)
+        
+        with pytest.raises(ValueError):
+            analysis.seasonal_decompose(parser, 'B01', 'AT', 1)
@@ -0,0 +1,69 @@
+import os
+import pytest
+from src.parser import FIGAROParser
+from src.errors import MissingYearError, SchemaMismatchError
+
+# Small extracts in the layout of the Eurostat bulk download: a TIME_PERIOD
+# header, padded cells, ':' markers and flags, several geos and sectors
+FIXTURES_DIR = os.path.join(os.path.dirname(__file__), 'fixtures')
+EUROSTAT_DIR = os.path.join(FIXTURES_DIR, 'eurostat')
+MALFORMED_FILE = os.path.join(FIXTURES_DIR, 'malformed.tsv')
+
+pytestmark = pytest.mark.integration
+
+@pytest.fixture
+def eurostat_parser():
+    return FIGAROParser(EUROSTAT_DIR)
+
+def test_eurostat_years(eurostat_parser):
+    assert eurostat_parser.get_available_years() == [2018, 2019, 2020]
+    assert eurostat_parser.resolve_year_column(eurostat_parser.imports_file, 2019) == '2019 '
+    
+    with pytest.raises(MissingYearError):
+        eurostat_parser.get_flow_data(2021)
+
+def test_eurostat_read_frame(eurostat_parser):
+    df = eurostat_parser.read_frame(eurostat_parser.imports_file)
+    assert list(df.columns) == ['freq', 'nace_r2', 'c_exp', 'unit', 'geo', '2018', '2019', '2020']
+    assert len(df) == 6
+    assert df['2020'].isna().sum() == 2
+    
+    flagged = eurostat_parser._read_and_clean_df(eurostat_parser.imports_file, with_flags=True)
+    assert list(flagged['2020_flag'].fillna('')) == ['p', '', 'p', 'p', '', '']
+    assert list(flagged['2018_flag'].fillna('')) == ['', '', '', 'c', '', '']
+
+@pytest.mark.parametrize('year, imports, exports', [
+    (2018, 4, 3),
+    (2019, 5, 4),
+    (2020, 3, 3)  # FR C10 imports are zero and excluded
+])
+def test_eurostat_row_counts(eurostat_parser, year, imports, exports):
+    flows = eurostat_parser.get_flow_data(year)
+    assert sum(f['target'] == 'Total Imports' for f in flows) == imports
+    assert sum(f['target'] == 'Total Exports' for f in flows) == exports
+
+def test_eurostat_nan_skipping(eurostat_parser):
+    flows = eurostat_parser.get_flow_data(2019, geo='FR')
+    assert [(f['source'], f['target'], f['value']) for f in flows] == [
+        ('C10', 'Total Imports', 14402.6),
+        ('C10', 'Total Exports', 15880.1)
+    ]
+    
+    kept = eurostat_parser.get_flow_data(2019, geo='FR', nan_mode='keep')
+    assert ('G46', 'Total Imports', None) in [(f['source'], f['target'], f['value']) for f in kept]
+
+def test_eurostat_sector_totals(eurostat_parser):
+    flows = eurostat_parser.get_flow_data(2019, nace_level=1)
+    totals = {}
+    for f in flows:
+        totals[(f['source'], f['target'])] = totals.get((f['source'], f['target']), 0.0) + f['value']
+    assert totals[('C', 'Total Imports')] == pytest.approx(2511.3 + 18033.5 + 14402.6)
+    assert totals[('A', 'Total Exports')] == pytest.approx(1377.0)
+
+def test_malformed_fixture(eurostat_parser):
+    assert eurostat_parser.metadata_mismatch_rows(MALFORMED_FILE) == [1]
+    
+    with pytest.raises(SchemaMismatchError, match="Rows 1"):
+        eurostat_parser.validate_file(MALFORMED_FILE)
+    with pytest.raises(SchemaMismatchError):
+        FIGAROParser(EUROSTAT_DIR, strict_metadata=True).read_frame(MALFORMED_FILE)
@@ -1,7 +1,30 @@
+import asyncio
 import pytest
+import threading
 import os
 import tempfile
-from src.parser import FIGAROParser
+import zipfile
+import codecs
+import json
+import gzip
+import shutil
+import pandas as pd
+from src import parser as parser_module
+from src.parser import FIGAROParser, Flow, FlowOptions, exact_sum
+from src.nace_labels import nace_label
+from src.errors import (
+    FlowError, MissingYearError, SchemaMismatchError, FlowParseError, DuplicateSeriesError, FlowDownloadError,
+    NegativeValueError, EmptyYearError, MixedUnitsError
+)
+
+def write_figaro_files(tmpdir, import_content, export_content):
+    """Write a FIGARO imports/exports file pair under tmpdir/figaro."""
+    figaro_dir = os.path.join(tmpdir, 'figaro')
+    os.makedirs(figaro_dir, exist_ok=True)
+    with open(os.path.join(figaro_dir, 'estat_naio_10_fgti.tsv'), 'w') as f:
+        f.write(import_content)
+    with open(os.path.join(figaro_dir, 'estat_naio_10_fgte.tsv'), 'w') as f:
+        f.write(export_content)
 
 @pytest.fixture
 def sample_data_dir():
@@ -34,6 +57,18 @@ def sample_data_dir():
             
         yield tmpdir
 
+@pytest.fixture
+def flagged_data_dir():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t2020\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t: \t1.5\n"
+            "A,B02,EXP_GO,MIO_EUR,BE\t1000 p\t2.5\n"
+            "A,B03,EXP_GO,MIO_EUR,DE\t2000\t: c\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        yield tmpdir
+
 def test_parser_initialization(sample_data_dir):
     parser = FIGAROParser(sample_data_dir)
     assert parser.data_dir == sample_data_dir
@@ -72,4 +107,1560 @@ def test_get_flow_data_with_filters(sample_data_dir):
     
     # Test with drop_nan=False (should include NaN entries)
     flows_with_nan = parser.get_flow_data(2019, drop_nan=False)
-    assert len(flows_with_nan) >= len(flows)  # Should have at least as many entries
\ No newline at end of file
+    assert len(flows_with_nan) >= len(flows)  # Should have at least as many entries
+
+def test_missing_markers_and_flags(flagged_data_dir):
+    parser = FIGAROParser(flagged_data_dir)
+    df = parser._read_and_clean_df(parser.imports_file, with_flags=True)
+    
+    assert pd.isna(df['2019'][0])
+    assert df['2019'][1] == 1000.0
+    assert df['2019'][2] == 2000.0
+    assert pd.isna(df['2020'][2])
+    assert list(df['2019_flag'].fillna('')) == ['', 'p', '']
+    assert df['2020_flag'][2] == 'c'
+    
+    # Flags are only attached on request
+    assert '2019_flag' not in parser._read_and_clean_df(parser.imports_file).columns
+
+def test_scientific_notation_values():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t1.23E6\n"
+            "A,B02,EXP_GO,MIO_EUR,BE\t1.23E6 p\n"
+            "A,B03,EXP_GO,MIO_EUR,DE\t4.5e-1e\n"
+            "A,B04,EXP_GO,MIO_EUR,FR\t2e+03 \n"
+            "A,B05,EXP_GO,MIO_EUR,IT\t: c\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        df = parser._read_and_clean_df(parser.imports_file, with_flags=True)
+    
+    assert list(df['2019'][:4]) == [1230000.0, 1230000.0, 0.45, 2000.0]
+    assert pd.isna(df['2019'][4])
+    assert list(df['2019_flag'].fillna('')) == ['', 'p', 'e', '', 'c']
+
+def test_get_flow_data_range(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    flows = parser.get_flow_data_range(2019, 2021)
+    
+    # 2021 is absent and skipped; each year matches the single-year extraction
+    assert {f['year'] for f in flows} == {2019, 2020}
+    for year in (2019, 2020):
+        single = parser.get_flow_data(year)
+        ranged = [
+            {k: v for k, v in f.items() if k != 'year'}
+            for f in flows if f['year'] == year
+        ]
+        assert ranged == single
+
+def test_get_flow_data_range_progress(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    calls = []
+    parser.get_flow_data_range(2019, 2021, progress=lambda completed, total: calls.append((completed, total)))
+    assert calls == [(n, 6) for n in range(1, 7)]
+    
+    # An exception raised by the callback aborts the extraction
+    def cancel(completed, total):
+        raise RuntimeError("cancelled")
+    with pytest.raises(RuntimeError, match="cancelled"):
+        parser.get_flow_data_range(2019, 2021, progress=cancel)
+
+def test_get_flow_data_range_deflators(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    nominal = parser.get_flow_data_range(2019, 2020)
+    real = parser.get_flow_data_range(2019, 2020, deflators={2019: 1.0, 2020: 2.0})
+    
+    assert [f['value'] for f in real] == [
+        f['value'] / (2.0 if f['year'] == 2020 else 1.0) for f in nominal
+    ]
+    with pytest.raises(ValueError, match="No deflator given for 2020"):
+        parser.get_flow_data_range(2019, 2020, deflators={2019: 1.0})
+    with pytest.raises(ValueError, match="positive"):
+        parser.get_flow_data_range(2019, 2020, deflators={2019: 1.0, 2020: -1.0})
+
+def test_get_flow_data_geo_filter(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    flows = parser.get_flow_data(2019, geo='AT')
+    assert [f['value'] for f in flows] == [100.5, 300.5]
+    
+    with pytest.raises(ValueError) as exc_info:
+        parser.get_flow_data(2019, geo='XX')
+    assert "Geo code XX not found" in str(exc_info.value)
+
+def test_read_frame(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    df = parser.read_frame(parser.imports_file)
+    assert list(df.columns) == ['freq', 'nace_r2', 'c_exp', 'unit', 'geo', '2019', '2020']
+    assert list(df['geo']) == ['AT', 'BE', 'DE', 'FR']
+    assert df['2020'][1] == 250.3
+
+def test_gzipped_input(sample_data_dir):
+    plain = FIGAROParser(sample_data_dir)
+    
+    with tempfile.TemporaryDirectory() as tmpdir:
+        figaro_dir = os.path.join(tmpdir, 'figaro')
+        os.makedirs(figaro_dir)
+        for source in (plain.imports_file, plain.exports_file):
+            target = os.path.join(figaro_dir, os.path.basename(source) + '.gz')
+            with open(source, 'rb') as f_in, gzip.open(target, 'wb') as f_out:
+                shutil.copyfileobj(f_in, f_out)
+        
+        gzipped = FIGAROParser(tmpdir)
+        assert gzipped.imports_file.endswith('.tsv.gz')
+        assert len(gzipped.read_frame(gzipped.imports_file)) == len(plain.read_frame(plain.imports_file))
+        assert gzipped.get_flow_data(2019) == plain.get_flow_data(2019)
+
+def test_custom_metadata_columns():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,indic_bt,unit,geo\t2019\n"
+            "A,PROD,I15,AT\t101.2\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        
+        parser = FIGAROParser(tmpdir, metadata_columns=['freq', 'indic_bt', 'unit', 'geo'])
+        df = parser.read_frame(parser.imports_file)
+        assert list(df.columns) == ['freq', 'indic_bt', 'unit', 'geo', '2019']
+        assert df['indic_bt'][0] == 'PROD'
+        
+        # The default five-field layout does not fit this file
+        with pytest.raises(ValueError) as exc_info:
+            FIGAROParser(tmpdir).read_frame(parser.imports_file)
+        assert "has 4 metadata fields but 5 names" in str(exc_info.value)
+
+def test_layout_without_unit():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,geo\t2019\t2020\n"
+            "A,B01,AT\t10\t20\n"
+            "A,B02,AT\t5\t:\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir, metadata_columns=['freq', 'nace_r2', 'geo'])
+        
+        # Flows from a layout without a unit field carry None as their unit
+        assert list(parser.iter_flow_data(2019, include_exports=False)) == [
+            {'source': 'B01', 'target': 'Total Imports', 'unit': None, 'value': 10},
+            {'source': 'B02', 'target': 'Total Imports', 'unit': None, 'value': 5}
+        ]
+        flows = parser.get_flow_data_range(2019, 2020, include_exports=False)
+        assert len(flows) == 3 and {flow['unit'] for flow in flows} == {None}
+        assert parser.get_flow_data_years([2020], include_exports=False) == [
+            {'source': 'B01', 'target': 'Total Imports', 'unit': None, 'year': 2020, 'value': 20.0}
+        ]
+
+def test_duplicates_without_unit():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,geo\t2019\n"
+            "A,B01,AT\t10\n"
+            "A,B02,AT\t5\n"
+            "A,B02,BE\t1\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir, metadata_columns=['freq', 'nace_r2', 'geo'])
+        
+        # Series are keyed on geo and nace_r2 alone
+        assert parser.get_flow_data(2019, include_exports=False) == [
+            {'source': 'B01', 'target': 'Total Imports', 'unit': None, 'value': 10},
+            {'source': 'B02', 'target': 'Total Imports', 'unit': None, 'value': 5},
+            {'source': 'B02', 'target': 'Total Imports', 'unit': None, 'value': 1}
+        ]
+        assert parser.get_flow_data(2019, include_exports=False, nace_level=1) == [
+            {'source': 'B', 'target': 'Total Imports', 'unit': None, 'value': 15},
+            {'source': 'B', 'target': 'Total Imports', 'unit': None, 'value': 1}
+        ]
+        
+        repeated_dir = os.path.join(tmpdir, 'repeated')
+        repeated = content + "A,B01,AT\t2\n"
+        write_figaro_files(repeated_dir, repeated, repeated)
+        with pytest.raises(DuplicateSeriesError, match=r"\(geo, nace_r2\): AT, B01"):
+            FIGAROParser(repeated_dir, metadata_columns=['freq', 'nace_r2', 'geo']).get_flow_data(2019)
+
+@pytest.mark.parametrize('separator', [',', ';'])
+def test_detects_separator(separator):
+    with tempfile.TemporaryDirectory() as tmpdir:
+        # Comma files carry one metadata field per column, other separators keep them packed
+        header_sep = separator if separator == ',' else ','
+        content = (
+            f"freq{header_sep}nace_r2{header_sep}c_exp{header_sep}unit{header_sep}geo{separator}2019{separator}2020\n"
+            f"A{header_sep}B01{header_sep}EXP_GO{header_sep}MIO_EUR{header_sep}AT{separator}100.5{separator}200.5\n"
+            f"A{header_sep}B02{header_sep}EXP_GO{header_sep}MIO_EUR{header_sep}BE{separator}150.3{separator}250.3\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        
+        assert parser._detect_separator(parser.imports_file) == separator
+        assert parser.get_available_regions() == ['AT', 'BE']
+        flows = parser.get_flow_data(2019)
+        assert [(f['source'], f['value']) for f in flows][:2] == [('B01', 100.5), ('B02', 150.3)]
+
+def test_separator_override(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir, separator='\t')
+    assert parser.get_available_years() == [2019, 2020]
+
+def test_explicit_pipe_separator():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo|2019|2020\n"
+            "A,B01,EXP_GO,MIO_EUR,AT|100.5|200.5\n"
+            "A,B02,EXP_GO,MIO_EUR,BE|150.3|250.3\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir, separator='|')
+        flows = parser.get_flow_data(2019)
+        assert [(f['source'], f['value']) for f in flows][:2] == [('B01', 100.5), ('B02', 150.3)]
+
+@pytest.mark.parametrize('separator', ['', '\t\t', '||'])
+def test_invalid_separator(sample_data_dir, separator):
+    with pytest.raises(ValueError, match="single character"):
+        FIGAROParser(sample_data_dir, separator=separator)
+
+def test_flows_include_unit(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    flows = parser.get_flow_data(2019)
+    assert all(f['unit'] == 'MIO_EUR' for f in flows)
+    assert all(f['unit'] == 'MIO_EUR' for f in parser.get_flow_data(2019, nace_level=1))
+
+def test_get_flow_data_top_n(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    flows = parser.get_flow_data(2019, top_n=1)
+    assert [(f['target'], f['value']) for f in flows] == [
+        ('Total Imports', 150.3),
+        ('Total Exports', 350.3)
+    ]
+
+def test_missing_year_error(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    with pytest.raises(ValueError) as exc_info:
+        parser.get_flow_data(2025)
+    assert "Year 2025 not found in estat_naio_10_fgti.tsv; available years: 2019..2020" in str(exc_info.value)
+
+def test_get_period_flow_data():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2020Q1\t2020Q2\n"
+            "Q,B01,EXP_GO,MIO_EUR,AT\t10.5\t11.5\n"
+            "Q,B02,EXP_GO,MIO_EUR,BE\t: \t12.5\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        
+        flows = parser.get_period_flow_data('2020Q1', include_exports=False)
+        assert [(f['source'], f['value']) for f in flows] == [('B01', 10.5)]
+        
+        with pytest.raises(ValueError) as exc_info:
+            parser.get_period_flow_data('2020M01')
+        assert "has frequency M but estat_naio_10_fgti.tsv contains Q data" in str(exc_info.value)
+        
+        with pytest.raises(ValueError):
+            parser.get_period_flow_data('2020-01')
+
+def test_parallel_flow_data_matches_serial(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    serial = (
+        parser._process_flow_file(parser.imports_file, 2019, 'Total Imports') +
+        parser._process_flow_file(parser.exports_file, 2019, 'Total Exports')
+    )
+    assert parser.get_flow_data(2019) == serial
+
+def test_frame_cache(sample_data_dir):
+    parser_module.clear_tsv_cache()
+    parser = FIGAROParser(sample_data_dir)
+    
+    first = parser._read_and_clean_df(parser.imports_file)
+    first['2019'] = 0.0  # Mutating a returned frame must not leak into the cache
+    assert parser._read_and_clean_df(parser.imports_file)['2019'][0] == 100.5
+    assert len(parser_module._FRAME_CACHE) == 1
+    
+    # A newer modification time invalidates the cached frame
+    stat = os.stat(parser.imports_file)
+    with open(parser.imports_file, 'a') as f:
+        f.write("A,B05,EXP_GO,MIO_EUR,IT\t1.0\t2.0\n")
+    os.utime(parser.imports_file, ns=(stat.st_atime_ns, stat.st_mtime_ns + 10**9))
+    assert len(parser._read_and_clean_df(parser.imports_file)) == 5
+    assert len(parser_module._FRAME_CACHE) == 1
+    
+    parser_module.clear_tsv_cache()
+    assert not parser_module._FRAME_CACHE
+
+def test_frame_cache_size(sample_data_dir, monkeypatch):
+    parser_module.clear_tsv_cache()
+    monkeypatch.setattr(parser_module, 'FRAME_CACHE_SIZE', 2)
+    parser = FIGAROParser(sample_data_dir)
+    
+    parser.get_year_frame(parser.imports_file, 2019)
+    parser.get_year_frame(parser.imports_file, 2020)
+    parser.get_year_frame(parser.imports_file, 2019)  # Refreshes the 2019 entry
+    parser.read_frame(parser.imports_file)
+    
+    # Each year subset is its own entry, and the least recently used is dropped
+    cached_columns = [key[4] for key in parser_module._FRAME_CACHE]
+    assert cached_columns == [('2019',), None]
+    
+    parser_module.clear_tsv_cache()
+
+def test_melt_flows(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    long_df = parser.melt_flows(parser.imports_file)
+    
+    # Three non-NaN rows per year; the FR row is all NaN
+    assert len(long_df) == 6
+    assert sorted(long_df['year'].unique()) == [2019, 2020]
+    row = long_df[(long_df['geo'] == 'BE') & (long_df['year'] == 2020)].iloc[0]
+    assert (row['nace_r2'], row['unit'], row['value']) == ('B02', 'MIO_EUR', 250.3)
+
+def test_get_flow_data_nace_prefix(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    flows = parser.get_flow_data(2019, nace_prefix=' b01')
+    assert [f['source'] for f in flows] == ['B01', 'B01']
+    
+    assert parser.get_flow_data(2019, nace_prefix='') == parser.get_flow_data(2019)
+    assert parser.get_flow_data(2019, nace_prefix='Z') == []
+
+def test_get_flow_data_include_exclude_nace(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    sources = lambda **filters: [f['source'] for f in parser.get_flow_data(2019, **filters)]
+    
+    assert sources(include_nace=[' b02', 'B03', 'Z99']) == ['B02', 'B02']
+    assert sources(exclude_nace=['b01 ']) == ['B02', 'B02']
+    # The blocklist removes codes from the allowlist
+    assert sources(include_nace=['B01', 'B02'], exclude_nace=['B02']) == ['B01', 'B01']
+    assert sources(include_nace=[]) == []
+    assert sources(include_nace=None, exclude_nace=None) == sources()
+
+def test_write_flows_parquet(sample_data_dir):
+    pytest.importorskip('pyarrow')
+    parser = FIGAROParser(sample_data_dir)
+    out_path = os.path.join(sample_data_dir, 'flows.parquet')
+    
+    rows = parser.write_flows_parquet(2019, out_path)
+    written = pd.read_parquet(out_path)
+    assert rows == len(written) == len(parser.get_flow_data(2019))
+    assert list(written.columns) == ['nace_r2', 'flow_type', 'value']
+    
+    with pytest.raises(FileNotFoundError):
+        parser.write_flows_parquet(2019, os.path.join(sample_data_dir, 'missing', 'flows.parquet'))
+
+def test_get_flow_data_glob(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    with tempfile.TemporaryDirectory() as tmpdir:
+        header = "freq,nace_r2,c_exp,unit,geo\t2019\n"
+        files = {
+            'imports_at.tsv': header + "A,B01,EXP_GO,MIO_EUR,AT\t10\n",
+            'imports_at_rev.tsv': header + "A,B01,EXP_GO,MIO_EUR,AT\t5\n",
+            'imports_be.tsv': header + "A,B01,EXP_GO,MIO_EUR,BE\t7\n",
+            'exports_at.tsv': header + "A,B02,IMP_GO,MIO_EUR,AT\t3\n",
+            'imports_bad.tsv.txt': "freq,nace_r2,c_exp,unit,geo\t2020\nA,B01,EXP_GO,MIO_EUR,AT\t1\n"
+        }
+        for name, content in files.items():
+            with open(os.path.join(tmpdir, name), 'w') as f:
+                f.write(content)
+        
+        flows = parser.get_flow_data_glob(
+            os.path.join(tmpdir, 'imports_*.tsv'),
+            os.path.join(tmpdir, 'exports_*.tsv'),
+            2019
+        )
+        assert [(f['source'], f['target'], f['value']) for f in flows] == [
+            ('B01', 'Total Imports', 15.0),
+            ('B01', 'Total Imports', 7.0),
+            ('B02', 'Total Exports', 3.0)
+        ]
+        
+        with pytest.raises(ValueError) as exc_info:
+            parser.get_flow_data_glob(
+                os.path.join(tmpdir, 'imports_*'),
+                os.path.join(tmpdir, 'exports_*.tsv'),
+                2019
+            )
+        assert 'imports_bad.tsv.txt' in str(exc_info.value)
+        
+        calls = []
+        parser.get_flow_data_glob(
+            os.path.join(tmpdir, 'imports_*.tsv'),
+            os.path.join(tmpdir, 'exports_*.tsv'),
+            2019,
+            progress=lambda completed, total: calls.append((completed, total))
+        )
+        assert calls == [(1, 4), (2, 4), (3, 4), (4, 4)]
+
+def test_typed_errors(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    with pytest.raises(MissingYearError):
+        parser.get_flow_data(2025)
+    with pytest.raises(FlowError):
+        FIGAROParser('nonexistent_dir')
+    with pytest.raises(SchemaMismatchError):
+        FIGAROParser(sample_data_dir, metadata_columns=['freq', 'geo']).get_flow_data(2019)
+    
+    with open(parser.imports_file, 'wb') as f:
+        f.write(b'')
+    with pytest.raises(FlowParseError):
+        parser._read_and_clean_df(parser.imports_file)
+
+def test_get_flow_data_from_bytes(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    with open(parser.imports_file, 'rb') as f:
+        imports_bytes = f.read()
+    with open(parser.exports_file, 'rb') as f:
+        exports_bytes = f.read()
+    
+    assert parser.get_flow_data_from_bytes(imports_bytes, exports_bytes, 2019) == parser.get_flow_data(2019)
+    assert parser.get_flow_data_from_bytes(gzip.compress(imports_bytes), exports_bytes, 2020) == parser.get_flow_data(2020)
+    
+    with pytest.raises(MissingYearError) as exc_info:
+        parser.get_flow_data_from_bytes(imports_bytes, exports_bytes, 2030)
+    assert "<bytes>" in str(exc_info.value)
+
+def test_null_values():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t2020\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\tN/A\t1.5\n"
+            "A,B02,EXP_GO,MIO_EUR,BE\t2.5\t- p\n"
+            "A,B03,EXP_GO,MIO_EUR,DE\t:\t3.5\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        
+        parser = FIGAROParser(tmpdir, null_values=['N/A', '-'])
+        df = parser._read_and_clean_df(parser.imports_file, with_flags=True)
+        assert pd.isna(df['2019'][0]) and pd.isna(df['2020'][1])
+        assert df['2020_flag'][1] == 'p'
+        assert ('missing', 2) in parser.flag_histogram(parser.imports_file)
+        
+        # ':' is no longer a null token but still cannot be read as a number
+        report = parser.get_quality_report(2019)
+        assert report['missing_markers'] == 2 and report['nan_skipped'] == 4
+        assert FIGAROParser(tmpdir).get_quality_report(2019)['missing_markers'] == 2
+    
+    with pytest.raises(ValueError):
+        FIGAROParser(tmpdir, null_values=[''])
+
+def test_skip_rows_after_header():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t2020\n"
+            "Units: million euro,,,,\tcurrent prices\t\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t100.5\t200.5\n"
+            "A,B02,EXP_GO,MIO_EUR,BE\t150.3\t250.3\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        
+        parser = FIGAROParser(tmpdir, skip_rows_after_header=1)
+        flows = parser.get_flow_data(2019)
+        assert [f['source'] for f in flows] == ['B01', 'B02', 'B01', 'B02']
+        assert parser.series_count(parser.imports_file) == 2
+        
+        # Without skipping, the description row is read as a bogus series
+        assert FIGAROParser(tmpdir).series_count(parser.imports_file) == 3
+    
+    with pytest.raises(ValueError):
+        FIGAROParser(tmpdir, skip_rows_after_header=-1)
+
+def test_long_layout():
+    wide = (
+        "freq,nace_r2,c_exp,unit,geo\t2019\t2020\n"
+        "A,B01,EXP_GO,MIO_EUR,AT\t100.5\t:\n"
+        "A,B02,EXP_GO,MIO_EUR,BE\t150.3 p\t250.3\n"
+    )
+    long = (
+        "freq,nace_r2,c_exp,unit,geo,year,value\n"
+        "A,B02,EXP_GO,MIO_EUR,BE,2020,250.3\n"
+        "A,B01,EXP_GO,MIO_EUR,AT,2019,100.5\n"
+        "A,B02,EXP_GO,MIO_EUR,BE,2019,150.3 p\n"
+    )
+    with tempfile.TemporaryDirectory() as wide_dir, tempfile.TemporaryDirectory() as long_dir:
+        write_figaro_files(wide_dir, wide, wide)
+        write_figaro_files(long_dir, long, long)
+        wide_parser = FIGAROParser(wide_dir)
+        long_parser = FIGAROParser(long_dir, layout='long')
+        
+        assert long_parser.get_available_years() == [2019, 2020]
+        for year in [2019, 2020]:
+            assert long_parser.get_flow_data(year) == wide_parser.get_flow_data(year)
+        
+        with open(long_parser.imports_file, 'w') as f:
+            f.write("nace_r2,geo,value\nB01,AT,1\n")
+        with pytest.raises(SchemaMismatchError, match="lacks the long layout columns year"):
+            long_parser.get_flow_data(2019)
+    
+    with pytest.raises(ValueError, match="layout"):
+        FIGAROParser(wide_dir, layout='tidy')
+
+def test_get_flow_data_single_file(sample_data_dir):
+    combined = os.path.join(sample_data_dir, 'combined.tsv')
+    with open(combined, 'w') as f:
+        f.write(
+            "freq,stk_flow,nace_r2,unit,geo\t2019\n"
+            "A,IMP,B01,MIO_EUR,AT\t10\n"
+            "A,EXP,B01,MIO_EUR,AT\t25\n"
+            "A,IMP,B02,MIO_EUR,AT\t0\n"
+            "A,EXP,B02,MIO_EUR,AT\t:\n"
+        )
+    parser = FIGAROParser(sample_data_dir, metadata_columns=['freq', 'stk_flow', 'nace_r2', 'unit', 'geo'])
+    
+    assert parser.get_flow_data_single_file(combined, 'stk_flow', 'IMP', 'EXP', 2019) == [
+        {'source': 'B01', 'target': 'Total Imports', 'unit': 'MIO_EUR', 'value': 10},
+        {'source': 'B01', 'target': 'Total Exports', 'unit': 'MIO_EUR', 'value': 25}
+    ]
+    with pytest.raises(SchemaMismatchError):
+        parser.get_flow_data_single_file(combined, 'indic', 'IMP', 'EXP', 2019)
+    with pytest.raises(ValueError, match="stk_flow EXPORT"):
+        parser.get_flow_data_single_file(combined, 'stk_flow', 'IMP', 'EXPORT', 2019)
+
+class FakeResponse:
+    def __init__(self, data, status=200):
+        self.data = data
+        self.status = status
+    
+    def __enter__(self):
+        return self
+    
+    def __exit__(self, *exc_info):
+        return False
+    
+    def read(self):
+        return self.data
+
+def test_get_flow_data_url(sample_data_dir, monkeypatch):
+    parser = FIGAROParser(sample_data_dir)
+    with open(parser.imports_file, 'rb') as f:
+        imports_bytes = f.read()
+    with open(parser.exports_file, 'rb') as f:
+        exports_bytes = f.read()
+    
+    served = {
+        'https://example.org/fgti.tsv': FakeResponse(imports_bytes),
+        'https://example.org/fgte.tsv.gz': FakeResponse(gzip.compress(exports_bytes)),
+        'https://example.org/missing.tsv': FakeResponse(b'', status=404)
+    }
+    requested = []
+    def fake_urlopen(request, timeout=None):
+        requested.append(request.full_url)
+        return served[request.full_url]
+    monkeypatch.setattr(parser_module.urllib.request, 'urlopen', fake_urlopen)
+    parser_module.clear_download_cache()
+    
+    urls = ('https://example.org/fgti.tsv', 'https://example.org/fgte.tsv.gz')
+    assert parser.get_flow_data_url(*urls, 2019) == parser.get_flow_data(2019)
+    assert parser.get_flow_data_url(*urls, 2020) == parser.get_flow_data(2020)
+    assert requested == list(urls)
+    
+    with pytest.raises(FlowDownloadError) as exc_info:
+        parser.get_flow_data_url('https://example.org/missing.tsv', urls[1], 2019)
+    assert "404" in str(exc_info.value)
+    parser_module.clear_download_cache()
+
+def test_flag_histogram(flagged_data_dir):
+    parser = FIGAROParser(flagged_data_dir)
+    assert parser.flag_histogram(parser.imports_file) == [('missing', 2), ('c', 1), ('p', 1)]
+
+def test_get_quality_report(flagged_data_dir):
+    parser = FIGAROParser(flagged_data_dir)
+    assert parser.get_quality_report(2020) == {
+        'total_rows': 6,
+        'nan_skipped': 2,
+        'missing_markers': 2,
+        'negative_values': 0,
+        'malformed_rows': 0,
+        'metadata_mismatches': 0,
+        'distinct_geos': 3
+    }
+
+def test_metadata_field_count_mismatch():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t100.5\n"
+            "A,B02,EXP_GO,MIO_EUR\t150.3\n"
+            "A,B03,EXP_GO,MIO_EUR,DE\t50.0\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        
+        parser = FIGAROParser(tmpdir)
+        assert parser.metadata_mismatch_rows(parser.imports_file) == [1]
+        assert parser.get_quality_report(2019)['metadata_mismatches'] == 2
+        assert pd.isna(parser.read_frame(parser.imports_file)['geo'][1])
+        
+        with pytest.raises(SchemaMismatchError, match="Rows 1 do not have 5 metadata fields"):
+            FIGAROParser(tmpdir, strict_metadata=True).read_frame(parser.imports_file)
+
+def test_skip_bad_rows():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t2020\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t100.5\t200.5\n"
+            "A,B02,EXP_GO,MIO_EUR,BE\t150.3\n"
+            "A,B03,EXP_GO,MIO_EUR,DE\t50.0\t60.0\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        
+        # By default the short row is padded with NaN
+        strict = FIGAROParser(tmpdir)
+        assert [f['source'] for f in strict.get_flow_data(2020, include_exports=False)] == ['B01', 'B03']
+        assert [f['source'] for f in strict.get_flow_data(2019, include_exports=False)] == ['B01', 'B02', 'B03']
+        assert strict.get_quality_report(2019)['malformed_rows'] == 2
+        
+        lenient = FIGAROParser(tmpdir, skip_bad_rows=True)
+        assert [f['source'] for f in lenient.get_flow_data(2019, include_exports=False)] == ['B01', 'B03']
+        report = lenient.get_quality_report(2019)
+        assert report['malformed_rows'] == 2
+        assert report['total_rows'] == 4
+
+def test_duplicate_series():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t10\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t5\n"
+            "A,B02,EXP_GO,MIO_EUR,AT\t3\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        
+        with pytest.raises(DuplicateSeriesError) as exc_info:
+            parser.get_flow_data(2019)
+        assert "AT, B01, MIO_EUR" in str(exc_info.value)
+        
+        summed = parser.get_flow_data(2019, include_exports=False, on_duplicate='sum')
+        assert [(f['source'], f['value']) for f in summed] == [('B01', 15.0), ('B02', 3.0)]
+        first = parser.get_flow_data(2019, include_exports=False, on_duplicate='first')
+        assert [(f['source'], f['value']) for f in first] == [('B01', 10.0), ('B02', 3.0)]
+
+@pytest.mark.parametrize('encoding', ['utf-8', 'latin-1'])
+def test_file_encoding(encoding):
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,EXP_GO,MIO_EUR,Curaçao\t10\n"
+        )
+        write_figaro_files(tmpdir, '', '')
+        parser = FIGAROParser(tmpdir, encoding=encoding)
+        for file_path in (parser.imports_file, parser.exports_file):
+            with open(file_path, 'wb') as f:
+                f.write(codecs.BOM_UTF8 + content.encode(encoding))
+        
+        df = parser.read_frame(parser.imports_file)
+        assert list(df.columns) == ['freq', 'nace_r2', 'c_exp', 'unit', 'geo', '2019']
+        assert parser.get_available_regions() == ['Curaçao']
+
+@pytest.mark.parametrize('header', [
+    "freq,nace_r2,c_exp,unit,geo\\time\t2019\n",
+    "freq,nace_r2,c_exp,unit,geo\\TIME_PERIOD\t2019\n",
+    "freq,nace_r2,c_exp,unit,geo\\time,2019\n"
+])
+def test_time_axis_label_stripped(header):
+    separator = '\t' if '\t' in header else ','
+    content = header + f"A,B01,EXP_GO,MIO_EUR,AT{separator}10\n"
+    with tempfile.TemporaryDirectory() as tmpdir:
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        
+        df = parser.read_frame(parser.imports_file)
+        assert list(df.columns) == ['freq', 'nace_r2', 'c_exp', 'unit', 'geo', '2019']
+        assert parser.get_available_regions() == ['AT']
+        assert [f['value'] for f in parser.get_flow_data(2019, geo='AT')] == [10, 10]
+
+def test_treat_zero_as_missing():
+    content = (
+        "freq,nace_r2,c_exp,unit,geo\t2019\n"
+        "A,B01,EXP_GO,MIO_EUR,AT\t0\n"
+        "A,B02,EXP_GO,MIO_EUR,AT\t:\n"
+        "A,B03,EXP_GO,MIO_EUR,AT\t5\n"
+    )
+    with tempfile.TemporaryDirectory() as tmpdir:
+        write_figaro_files(tmpdir, content, content)
+        
+        values = FIGAROParser(tmpdir).read_frame(os.path.join(tmpdir, 'figaro', 'estat_naio_10_fgti.tsv'))['2019']
+        assert values[0] == 0
+        assert pd.isna(values[1])
+        
+        parser = FIGAROParser(tmpdir, treat_zero_as_missing=True)
+        assert pd.isna(parser.read_frame(parser.imports_file)['2019'][0])
+        flows = parser.get_flow_data(2019, include_exports=False, nan_mode='keep')
+        assert [(f['source'], f['value']) for f in flows] == [('B01', None), ('B02', None), ('B03', 5)]
+
+def test_resolve_year_column(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    assert parser.resolve_year_column(parser.imports_file, 2019) == '2019'
+    assert parser.resolve_year_column(parser.imports_file, 2025) is None
+    
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = "freq,nace_r2,c_exp,unit,geo\t2019.0\t 2020 \nA,B01,EXP_GO,MIO_EUR,AT\t1\t2\n"
+        write_figaro_files(tmpdir, content, content)
+        padded = FIGAROParser(tmpdir)
+        assert padded.resolve_year_column(padded.imports_file, 2019) == '2019.0'
+        assert padded.resolve_year_column(padded.imports_file, 2020) == ' 2020 '
+
+def test_merge_yearly_files():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        files = {
+            'trade_2020.tsv': "freq,nace_r2,c_exp,unit,geo\t2020\nA,B01,EXP_GO,MIO_EUR,AT\t10\nA,B02,EXP_GO,MIO_EUR,BE\t:\n",
+            'trade_2021.tsv': "freq,nace_r2,c_exp,unit,geo\tOBS_VALUE\nA,B01,EXP_GO,MIO_EUR,AT\t12.5\n",
+            'trade_wide.tsv': "freq,nace_r2,c_exp,unit,geo\t2020\t2021\nA,B01,EXP_GO,MIO_EUR,AT\t1\t2\n"
+        }
+        for name, content in files.items():
+            with open(os.path.join(tmpdir, name), 'w') as f:
+                f.write(content)
+        write_figaro_files(tmpdir, files['trade_2020.tsv'], files['trade_2020.tsv'])
+        parser = FIGAROParser(tmpdir)
+        
+        merged = parser.merge_yearly_files([
+            (2020, os.path.join(tmpdir, 'trade_2020.tsv')),
+            (2021, os.path.join(tmpdir, 'trade_2021.tsv'))
+        ])
+        assert merged == [('B01', 'AT', 2020, 10.0), ('B01', 'AT', 2021, 12.5)]
+        
+        with pytest.raises(SchemaMismatchError, match="found 2: 2020, 2021"):
+            parser.merge_yearly_files([(2020, os.path.join(tmpdir, 'trade_wide.tsv'))])
+
+@pytest.mark.parametrize('separator, content, error, message', [
+    ('\t', "freq;nace_r2;c_exp;unit;geo;2019\nA;B01;EXP_GO;MIO_EUR;AT;10\n", FlowParseError, "check the separator"),
+    (None, "freq,nace_r2,c_exp,unit,geo\tlabel\nA,B01,EXP_GO,MIO_EUR,AT\tx\n", SchemaMismatchError, "no year or period columns"),
+    (None, "freq,nace_r2,unit,geo\t2019\nA,B01,MIO_EUR,AT\t10\n", SchemaMismatchError, "has 4 metadata fields"),
+    (None, "freq,nace_r2,c_exp,unit,geo\t2019\nA,B01,EXP_GO,MIO_EUR,AT\t10\nA,B02,MIO_EUR,AT\t10\n", SchemaMismatchError, "Rows 1")
+])
+def test_validate_file(sample_data_dir, separator, content, error, message):
+    parser = FIGAROParser(sample_data_dir, separator=separator)
+    assert parser.validate_file(parser.imports_file) is None
+    
+    invalid = os.path.join(sample_data_dir, 'upload.tsv')
+    with open(invalid, 'w') as f:
+        f.write(content)
+    with pytest.raises(error, match=message):
+        parser.validate_file(invalid)
+
+def test_detect_frequency(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    assert parser.detect_frequency(parser.imports_file) == ['A']
+    
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t2019M01\n"
+            "M,B01,EXP_GO,MIO_EUR,AT\t:\t1\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t12\t:\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        mixed = FIGAROParser(tmpdir)
+        assert mixed.detect_frequency(mixed.exports_file) == ['A', 'M']
+        
+        with pytest.raises(SchemaMismatchError):
+            FIGAROParser(tmpdir, metadata_columns=['f', 'nace_r2', 'c_exp', 'unit', 'geo']).detect_frequency(mixed.exports_file)
+
+def test_coverage_by_year(sample_data_dir, flagged_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    assert parser.coverage_by_year(parser.imports_file) == [(2019, 3, 4), (2020, 3, 4)]
+    
+    # Missing markers count as null, flagged values as reported
+    flagged = FIGAROParser(flagged_data_dir)
+    assert flagged.coverage_by_year(flagged.imports_file) == [(2019, 2, 3), (2020, 2, 3)]
+
+def test_column_map():
+    content = (
+        "country,sector,freq,c_exp,unit,2019\n"
+        "AT,B01,A,EXP_GO,MIO_EUR,10\n"
+        "BE,B02,A,EXP_GO,MIO_EUR,20\n"
+    )
+    with tempfile.TemporaryDirectory() as tmpdir:
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir, column_map={'nace_r2': 'sector', 'geo': 'country'})
+        
+        assert list(parser.read_frame(parser.imports_file).columns) == ['freq', 'nace_r2', 'c_exp', 'unit', 'geo', '2019']
+        flows = parser.get_flow_data(2019, geo='BE', with_geo=True)
+        assert [(f['geo'], f['source'], f['value']) for f in flows] == [('BE', 'B02', 20), ('BE', 'B02', 20)]
+        
+        with pytest.raises(ValueError, match="Unknown fields in column_map: sector"):
+            FIGAROParser(tmpdir, column_map={'sector': 'nace'})
+
+def test_metadata_columns_after_values():
+    content = (
+        "2019,sector,country,freq,c_exp,unit\n"
+        "10,B01,AT,A,EXP_GO,MIO_EUR\n"
+        "20,B02,BE,A,EXP_GO,MIO_EUR\n"
+    )
+    with tempfile.TemporaryDirectory() as tmpdir:
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir, column_map={'nace_r2': 'sector', 'geo': 'country'})
+        
+        df = parser.read_frame(parser.imports_file)
+        assert list(df.columns) == ['freq', 'nace_r2', 'c_exp', 'unit', 'geo', '2019']
+        assert list(df['2019']) == [10, 20]
+        assert list(df['geo']) == ['AT', 'BE']
+
+@pytest.mark.parametrize('thousands_separator, decimal_comma, values', [
+    (',', False, ['1,234,567', '2,500.5 p', ':']),
+    (' ', False, ['1 234 567', '2\u00a0500.5 p', ':']),
+    ('.', True, ['1.234.567', '2.500,5 p', ':'])
+])
+def test_thousands_separator(thousands_separator, decimal_comma, values):
+    content = "freq,nace_r2,c_exp,unit,geo\t2019\n" + "".join(
+        f"A,B0{i},EXP_GO,MIO_EUR,AT\t{value}\n" for i, value in enumerate(values, start=1)
+    )
+    with tempfile.TemporaryDirectory() as tmpdir:
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir, thousands_separator=thousands_separator, decimal_comma=decimal_comma)
+        
+        df = parser.read_frame(parser.imports_file)
+        assert list(df['nace_r2']) == ['B01', 'B02', 'B03']
+        assert list(df['2019'][:2]) == [1234567.0, 2500.5]
+        assert pd.isna(df['2019'][2])
+
+@pytest.mark.parametrize('thousands_separator, decimal_comma', [(',', True), ('.', False), ('', False)])
+def test_invalid_thousands_separator(sample_data_dir, thousands_separator, decimal_comma):
+    with pytest.raises(ValueError):
+        FIGAROParser(sample_data_dir, thousands_separator=thousands_separator, decimal_comma=decimal_comma)
+
+def test_unit_distribution():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,EXP_GO,THS_T,AT\t7\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t100\n"
+            "A,B02,EXP_GO,MIO_EUR,AT\t:\n"
+            "A,B02,EXP_GO,THS_T,BE\t3\n"
+            "A,B03,EXP_GO,MIO_EUR,BE\t5\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        
+        assert parser.unit_distribution(parser.imports_file) == [('MIO_EUR', 3), ('THS_T', 2)]
+        with pytest.raises(SchemaMismatchError):
+            FIGAROParser(tmpdir, metadata_columns=['freq', 'nace_r2', 'c_exp', 'u', 'geo']).unit_distribution(parser.imports_file)
+
+def test_year_range(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    assert parser.year_range(parser.imports_file) == (2019, 2020)
+    
+    no_years = os.path.join(sample_data_dir, 'quarterly.tsv')
+    with open(no_years, 'w') as f:
+        f.write("freq,nace_r2,c_exp,unit,geo\t2019Q1\nQ,B01,EXP_GO,MIO_EUR,AT\t1\n")
+    with pytest.raises(SchemaMismatchError, match="no year columns"):
+        parser.year_range(no_years)
+
+def test_schema_listing(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    assert parser.list_years(parser.exports_file) == [2019, 2020]
+    assert parser.list_geos(parser.exports_file) == ['AT', 'BE', 'DE', 'FR']
+    assert parser.list_nace(parser.imports_file) == ['B01', 'B02', 'B03', 'B04']
+
+def test_list_series_keys(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    keys = parser.list_series_keys(parser.imports_file)
+    assert keys[0] == ('A', 'B01', 'EXP_GO', 'MIO_EUR', 'AT')
+    assert [key[1] for key in keys] == ['B01', 'B02', 'B03', 'B04']
+    
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t2020\n"
+            "A,B02,EXP_GO,MIO_EUR,BE\t1\t2\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t:\tbad\n"
+            "A,B02,EXP_GO,MIO_EUR,BE\t3\t4\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        duplicated = FIGAROParser(tmpdir)
+        assert duplicated.list_series_keys(duplicated.exports_file) == [
+            ('A', 'B01', 'EXP_GO', 'MIO_EUR', 'AT'),
+            ('A', 'B02', 'EXP_GO', 'MIO_EUR', 'BE')
+        ]
+
+@pytest.mark.parametrize('nan_mode, expected', [
+    ('skip', [('B02', 1000.0), ('B03', 2000.0)]),
+    ('zero', [('B01', 0.0), ('B02', 1000.0), ('B03', 2000.0)]),
+    ('keep', [('B01', None), ('B02', 1000.0), ('B03', 2000.0)])
+])
+def test_nan_modes(flagged_data_dir, nan_mode, expected):
+    parser = FIGAROParser(flagged_data_dir)
+    flows = parser.get_flow_data(2019, include_exports=False, nan_mode=nan_mode)
+    assert [(f['source'], f['value']) for f in flows] == expected
+
+def test_year_projection(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    assert list(parser.read_frame(parser.imports_file, years=[2020]).columns) == [
+        'freq', 'nace_r2', 'c_exp', 'unit', 'geo', '2020'
+    ]
+    assert list(parser._read_and_clean_df(parser.imports_file, columns=['2019']).columns) == [
+        'freq', 'nace_r2', 'c_exp', 'unit', 'geo', '2019'
+    ]
+
+def test_get_flow_data_scale(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    raw = parser.get_flow_data(2019)
+    scaled = parser.get_flow_data(2019, scale=1000.0)
+    assert [f['value'] for f in scaled] == pytest.approx([f['value'] / 1000 for f in raw])
+    assert all(f['unit'] == 'MIO_EUR' for f in scaled)
+    
+    for scale in (0.0, -1.0):
+        with pytest.raises(ValueError):
+            parser.get_flow_data(2019, scale=scale)
+
+def test_decimal_comma():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t1 234,5\n"
+            "A,B02,EXP_GO,MIO_EUR,BE\t12,25 p\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        
+        parser = FIGAROParser(tmpdir, decimal_comma=True)
+        df = parser.read_frame(parser.imports_file)
+        assert list(df['2019']) == [1234.5, 12.25]
+        assert list(df['geo']) == ['AT', 'BE']
+
+def test_min_value_uses_magnitude(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    assert parser.get_flow_data(2019, min_value=None) == parser.get_flow_data(2019)
+    
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t-500\n"
+            "A,B02,EXP_GO,MIO_EUR,AT\t50\n"
+            "A,B03,EXP_GO,MIO_EUR,AT\t-5\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        flows = FIGAROParser(tmpdir).get_flow_data(2019, min_value=40.0, include_exports=False)
+        assert [(f['source'], f['value']) for f in flows] == [('B01', -500.0), ('B02', 50.0)]
+
+def test_parquet_input_round_trip(sample_data_dir):
+    pytest.importorskip('pyarrow')
+    plain = FIGAROParser(sample_data_dir)
+    
+    with tempfile.TemporaryDirectory() as tmpdir:
+        figaro_dir = os.path.join(tmpdir, 'figaro')
+        os.makedirs(figaro_dir)
+        for source in (plain.imports_file, plain.exports_file):
+            target = os.path.join(figaro_dir, os.path.basename(source).replace('.tsv', '.parquet'))
+            plain.read_frame(source).to_parquet(target, index=False)
+        
+        parquet = FIGAROParser(tmpdir)
+        assert parquet.imports_file.endswith('.parquet')
+        assert parquet.get_flow_data(2019) == plain.get_flow_data(2019)
+        assert parquet.get_available_years() == [2019, 2020]
+        
+        unsupported = os.path.join(tmpdir, 'flows.xlsx')
+        with open(unsupported, 'wb') as f:
+            f.write(b'not a flow file')
+        with pytest.raises(FlowParseError) as exc_info:
+            parquet.read_frame(unsupported)
+        assert "Unsupported input format: flows.xlsx" in str(exc_info.value)
+
+@pytest.mark.parametrize('extension', ['.arrow', '.feather'])
+def test_arrow_input_round_trip(sample_data_dir, extension):
+    pytest.importorskip('pyarrow')
+    plain = FIGAROParser(sample_data_dir)
+    
+    with tempfile.TemporaryDirectory() as tmpdir:
+        figaro_dir = os.path.join(tmpdir, 'figaro')
+        os.makedirs(figaro_dir)
+        for source in (plain.imports_file, plain.exports_file):
+            target = os.path.join(figaro_dir, os.path.basename(source).replace('.tsv', extension))
+            plain.read_frame(source).to_feather(target)
+        
+        arrow = FIGAROParser(tmpdir)
+        assert arrow.imports_file.endswith(extension)
+        assert arrow.get_flow_data(2019) == plain.get_flow_data(2019)
+        
+        incomplete = os.path.join(tmpdir, 'incomplete' + extension)
+        plain.read_frame(plain.imports_file).drop(columns=['geo']).to_feather(incomplete)
+        with pytest.raises(SchemaMismatchError, match="lacks the metadata columns geo"):
+            arrow.read_frame(incomplete)
+
+def test_flow_matrix(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    geos, nace_codes, values = parser.flow_matrix(2019)
+    
+    assert geos == sorted(geos)
+    assert nace_codes == sorted(nace_codes)
+    assert len(values) == len(geos)
+    assert all(len(row) == len(nace_codes) for row in values)
+    
+    frame = parser.get_year_frame(parser.imports_file, 2019)
+    totals = frame.groupby(['geo', 'nace_r2'])['value'].sum(min_count=1)
+    for i, geo in enumerate(geos):
+        for j, nace in enumerate(nace_codes):
+            if (geo, nace) in totals.index and not pd.isna(totals[(geo, nace)]):
+                assert values[i][j] == pytest.approx(totals[(geo, nace)])
+            else:
+                assert pd.isna(values[i][j])
+    
+    with pytest.raises(ValueError, match="Unknown flow type"):
+        parser.flow_matrix(2019, flow_type="Total Transit")
+
+def test_pivot_years(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    assert parser.pivot_years('AT', 'Total Exports') == ([2019, 2020], [('B01', [300.5, 400.5])])
+    
+    years, rows = parser.pivot_years('FR')
+    assert years == [2019, 2020]
+    assert rows[0][0] == 'B04' and all(pd.isna(value) for value in rows[0][1])
+    
+    with pytest.raises(ValueError, match="Geo code XX"):
+        parser.pivot_years('XX')
+
+def test_pivot_years_mixed_units():
+    content = (
+        "freq,nace_r2,c_exp,unit,geo\t2019\t2020\n"
+        "A,B01,EXP_GO,MIO_EUR,AT\t100\t110\n"
+        "A,B01,EXP_GO,THS_T,AT\t7\t8\n"
+    )
+    with tempfile.TemporaryDirectory() as tmpdir:
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        
+        with pytest.raises(MixedUnitsError, match="mixes units MIO_EUR, THS_T"):
+            parser.pivot_years('AT')
+        assert parser.pivot_years('AT', unit_filter='THS_T') == ([2019, 2020], [('B01', [7.0, 8.0])])
+
+def test_exact_sum():
+    # Floats are rounded once, after summing
+    assert exact_sum(pd.Series([1e16, 1.5, -1e16])) == 1.5
+    # Whole numbers stay ints, where converting them to floats first would lose the 1
+    assert exact_sum(pd.Series([2**53 + 1, -2**53], dtype='Int64')) == 1
+    
+    assert exact_sum(pd.Series([None, 2.0])) == 2.0
+    assert pd.isna(exact_sum(pd.Series([None], dtype='Float64'), min_count=1))
+    assert exact_sum(pd.Series([], dtype=float)) == 0
+
+def test_get_flow_data_map():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t100\n"
+            "A,B01,EXP_GO,MIO_EUR,BE\t50\n"
+            "A,B02,EXP_GO,MIO_EUR,AT\t30\n"
+            "A,B02,EXP_GO,MIO_EUR,AT\t5\n"
+            "A,B03,EXP_GO,MIO_EUR,AT\t:\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        
+        assert parser.get_flow_data_map(2019, 'Total Exports', on_duplicate='sum') == {'B01': 150.0, 'B02': 35.0}
+        assert parser.get_flow_data_map(2019, on_duplicate='first') == {'B01': 150.0, 'B02': 30.0}
+        with pytest.raises(DuplicateSeriesError):
+            parser.get_flow_data_map(2019)
+        with pytest.raises(ValueError, match="Unknown flow type"):
+            parser.get_flow_data_map(2019, 'Total Transit')
+
+def test_get_flow_data_logs_summary(sample_data_dir, caplog):
+    parser = FIGAROParser(sample_data_dir)
+    
+    with caplog.at_level('DEBUG', logger='src.parser'):
+        flows = parser.get_flow_data(2019)
+    
+    assert f"Extracted {len(flows)} flows for 2019" in caplog.text
+    assert "Matched year column 2019" in caplog.text
+    assert "Skipped" in caplog.text
+
+def test_get_flow_page(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    flows, total = parser.get_flow_page(2019)
+    
+    assert total == len(parser.get_flow_data(2019))
+    assert flows == sorted(flows, key=lambda flow: (flow['source'], flow['target'], flow['unit']))
+    
+    page, page_total = parser.get_flow_page(2019, offset=1, limit=2)
+    assert page == flows[1:3]
+    assert page_total == total
+    
+    assert parser.get_flow_page(2019, offset=total + 5) == ([], total)
+    
+    with pytest.raises(ValueError):
+        parser.get_flow_page(2019, offset=-1)
+
+def test_get_flow_page_missing_unit(sample_data_dir, monkeypatch):
+    parser = FIGAROParser(sample_data_dir)
+    flows = [
+        {'source': 'B01', 'target': 'Total Imports', 'unit': 'MIO_EUR', 'value': 1.0},
+        {'source': 'B01', 'target': 'Total Imports', 'unit': None, 'value': 2.0}
+    ]
+    monkeypatch.setattr(parser, 'get_flow_data', lambda year, **filters: list(flows))
+    
+    # Units compare as text, so a missing unit sorts without a TypeError
+    page, total = parser.get_flow_page(2019)
+    assert total == 2
+    assert [flow['unit'] for flow in page] == ['MIO_EUR', None]
+
+def test_get_flow_data_typed(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    flows = parser.get_flow_data_typed(2019, top_n=1)
+    
+    assert flows == [
+        Flow(nace=f['source'], flow_type=f['target'], value=f['value'], unit=f['unit'])
+        for f in parser.get_flow_data(2019, top_n=1)
+    ]
+    assert flows[0].flow_type == 'Total Imports'
+    assert repr(flows[0]).startswith("Flow(nace=")
+
+def test_year_header_with_whitespace():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t 2020\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t100.5\t200.5\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        assert parser.get_available_years() == [2019, 2020]
+        assert [f['value'] for f in parser.get_flow_data(2020)] == [200.5, 200.5]
+
+def test_numeric_year_headers():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019.0\t2020.0\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t100.5\t200.5\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        assert parser.get_available_years() == [2019, 2020]
+        assert [f['value'] for f in parser.get_flow_data(2020)] == [200.5, 200.5]
+    
+    assert parser._column_name(2020) == '2020'
+
+def test_streaming_matches_in_memory(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    
+    for year in (2019, 2020):
+        assert parser.get_flow_data(year, streaming=True) == parser.get_flow_data(year)
+    
+    streamed = parser._stream_year_frame(parser.imports_file, 2019, chunksize=1)
+    pd.testing.assert_frame_equal(streamed, parser.get_year_frame(parser.imports_file, 2019))
+    
+    with pytest.raises(MissingYearError):
+        parser.get_flow_data(1999, streaming=True)
+
+def test_get_flow_data_years(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    flows = parser.get_flow_data_years([2020, 2019])
+    
+    assert [f['year'] for f in flows] == sorted((f['year'] for f in flows), reverse=True)
+    expected = parser.get_flow_data_range(2020, 2020) + parser.get_flow_data_range(2019, 2019)
+    assert sorted(flows, key=lambda f: (f['year'], f['target'], f['source'])) == (
+        sorted(expected, key=lambda f: (f['year'], f['target'], f['source']))
+    )
+    
+    with pytest.raises(MissingYearError, match="1999, 2030 not found"):
+        parser.get_flow_data_years([2019, 1999, 2030])
+
+def test_get_flow_data_round_decimals(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    raw = parser.get_flow_data(2019, scale=7)
+    
+    rounded = parser.get_flow_data(2019, scale=7, round_decimals=0)
+    assert all(f['value'] == float(int(f['value'])) for f in rounded)
+    assert [f['value'] for f in rounded] == [round(f['value']) for f in raw]
+    
+    assert [f['value'] for f in parser.get_flow_data(2019, scale=7, round_decimals=2)] == (
+        [round(f['value'], 2) for f in raw]
+    )
+    
+    with pytest.raises(ValueError):
+        parser.get_flow_data(2019, round_decimals=-1)
+
+def test_cached_metadata_is_categorical(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    cached = parser._read_and_clean_df(parser.imports_file)
+    
+    assert list(cached.columns[:5]) == parser.metadata_columns
+    assert all(isinstance(cached[col].dtype, pd.CategoricalDtype) for col in parser.metadata_columns)
+    
+    frame = parser.read_frame(parser.imports_file)
+    assert all(frame[col].dtype == object for col in parser.metadata_columns)
+
+def test_get_flow_data_arrow(sample_data_dir):
+    pytest.importorskip('pyarrow')
+    parser = FIGAROParser(sample_data_dir)
+    table = parser.get_flow_data_arrow(2019)
+    
+    assert table.column_names == ['nace_r2', 'flow_type', 'value']
+    df = table.to_pandas()
+    assert len(df) == len(parser.get_flow_data(2019))
+    pd.testing.assert_frame_equal(df, parser.flows_frame(parser.get_flow_data(2019)))
+
+def test_schema_mismatch_between_files():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        import_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t2020\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t100.5\t200.5\n"
+        )
+        export_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t2021\n"
+            "A,B01,IMP_GO,MIO_EUR,AT\t300.5\t400.5\n"
+        )
+        write_figaro_files(tmpdir, import_content, export_content)
+        parser = FIGAROParser(tmpdir)
+        
+        with pytest.raises(SchemaMismatchError) as exc_info:
+            parser.get_flow_data(2019)
+        message = str(exc_info.value)
+        assert "period columns only in estat_naio_10_fgti.tsv: 2020" in message
+        assert "period columns only in estat_naio_10_fgte.tsv: 2021" in message
+        
+        # Non-strict mode processes any year the two files have in common
+        assert [f['value'] for f in parser.get_flow_data(2019, strict=False)] == [100.5, 300.5]
+        with pytest.raises(MissingYearError):
+            parser.get_flow_data(2020, strict=False)
+        assert len(parser.get_flow_data(2020, include_exports=False)) == 1
+
+def test_get_flow_data_async(sample_data_dir, monkeypatch):
+    parser = FIGAROParser(sample_data_dir)
+    expected = {year: parser.get_flow_data(year) for year in (2019, 2020)}
+    
+    # Both calls must be in flight at once for the barrier to release them
+    barrier = threading.Barrier(2, timeout=5)
+    get_flow_data = parser.get_flow_data
+    def waiting_get_flow_data(year, **filters):
+        assert threading.current_thread() is not threading.main_thread()
+        barrier.wait()
+        return get_flow_data(year, **filters)
+    monkeypatch.setattr(parser, 'get_flow_data', waiting_get_flow_data)
+    
+    async def fetch_both():
+        return await asyncio.gather(parser.get_flow_data_async(2019), parser.get_flow_data_async(2020))
+    
+    assert asyncio.run(fetch_both()) == [expected[2019], expected[2020]]
+
+def test_zip_input(sample_data_dir):
+    plain = FIGAROParser(sample_data_dir)
+    
+    with tempfile.TemporaryDirectory() as tmpdir:
+        figaro_dir = os.path.join(tmpdir, 'figaro')
+        os.makedirs(figaro_dir)
+        for source in (plain.imports_file, plain.exports_file):
+            name = os.path.basename(source)
+            with zipfile.ZipFile(os.path.join(figaro_dir, name.replace('.tsv', '.zip')), 'w') as archive:
+                archive.write(source, name)
+                archive.writestr('README.md', 'Eurostat bulk download')
+        
+        zipped = FIGAROParser(tmpdir)
+        assert zipped.imports_file.endswith('.zip')
+        assert zipped.get_flow_data(2019) == plain.get_flow_data(2019)
+        
+        ambiguous = os.path.join(tmpdir, 'both.zip')
+        with zipfile.ZipFile(ambiguous, 'w') as archive:
+            archive.write(plain.imports_file, 'fgti.tsv')
+            archive.write(plain.exports_file, 'fgte.tsv')
+        with pytest.raises(FlowParseError, match="several TSV files"):
+            zipped.read_frame(ambiguous)
+        assert len(FIGAROParser(tmpdir, zip_entry='fgte.tsv').read_frame(ambiguous)) == 4
+        
+        empty = os.path.join(tmpdir, 'empty.zip')
+        with zipfile.ZipFile(empty, 'w') as archive:
+            archive.writestr('notes.md', 'nothing here')
+        with pytest.raises(FlowParseError, match="contains no TSV file"):
+            zipped.read_frame(empty)
+
+def test_negative_values(caplog):
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t100.5\n"
+            "A,B02,EXP_GO,MIO_EUR,BE\t-15\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        
+        with caplog.at_level('WARNING', logger='src.parser'):
+            flows = parser.get_flow_data(2019)
+        assert [f['value'] for f in flows] == [100.5, -15.0, 100.5, -15.0]
+        assert "1 negative values in estat_naio_10_fgti.tsv" in caplog.text
+        
+        with pytest.raises(NegativeValueError) as exc_info:
+            parser.get_flow_data(2019, reject_negative=True)
+        assert "(B02, BE, -15.0)" in str(exc_info.value)
+
+def test_empty_year(caplog):
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t2020\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t: \t1.5\n"
+            "A,B02,EXP_GO,MIO_EUR,BE\t: c\t2.5\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        
+        with caplog.at_level('WARNING', logger='src.parser'):
+            assert parser.get_flow_data(2019) == []
+        assert "Year 2019 has no reported values in estat_naio_10_fgti.tsv" in caplog.text
+        
+        with pytest.raises(EmptyYearError):
+            parser.get_flow_data(2019, error_on_empty_year=True)
+        # A filter matching nothing is not an empty year
+        assert parser.get_flow_data(2020, nace_prefix='C', error_on_empty_year=True) == []
+
+def test_get_flow_data_max_threads(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    assert parser.get_flow_data(2019, max_threads=1) == parser.get_flow_data(2019)
+    
+    with pytest.raises(ValueError):
+        parser.get_flow_data(2019, max_threads=0)
+
+def test_configure_threadpool(sample_data_dir, monkeypatch):
+    monkeypatch.setattr(parser_module, '_THREADPOOL_SIZE', None)
+    parser = FIGAROParser(sample_data_dir)
+    expected = parser.get_flow_data(2019)
+    
+    parser_module.configure_threadpool(1)
+    assert parser_module._THREADPOOL_SIZE == 1
+    assert parser.get_flow_data(2019) == expected
+    
+    with pytest.raises(ValueError, match="at least 1"):
+        parser_module.configure_threadpool(0)
+
+def test_get_flow_data_with_geo(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    flows = parser.get_flow_data(2019, with_geo=True)
+    
+    assert [(f['geo'], f['source'], f['target'], f['value']) for f in flows] == [
+        ('AT', 'B01', 'Total Imports', 100.5),
+        ('BE', 'B02', 'Total Imports', 150.3),
+        ('AT', 'B01', 'Total Exports', 300.5),
+        ('BE', 'B02', 'Total Exports', 350.3)
+    ]
+    assert [{k: v for k, v in f.items() if k != 'geo'} for f in flows] == parser.get_flow_data(2019)
+    assert {f['geo'] for f in parser.get_flow_data(2019, with_geo=True, nace_level=1)} == {'AT', 'BE'}
+
+def test_get_flow_data_json(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    records = json.loads(parser.get_flow_data_json(2019, geo='AT'))
+    
+    assert records == [
+        {'nace_r2': 'B01', 'flow_type': 'Total Imports', 'value': 100.5},
+        {'nace_r2': 'B01', 'flow_type': 'Total Exports', 'value': 300.5}
+    ]
+
+def test_melt_to_parquet(sample_data_dir):
+    pytest.importorskip('pyarrow')
+    parser = FIGAROParser(sample_data_dir)
+    out_path = os.path.join(sample_data_dir, 'melted.parquet')
+    
+    rows = parser.melt_to_parquet(parser.imports_file, 'Total Imports', out_path, chunksize=2)
+    written = pd.read_parquet(out_path)
+    expected = parser.melt_flows(parser.imports_file)
+    
+    assert rows == len(written) == len(expected)
+    assert list(written.columns) == ['freq', 'nace_r2', 'c_exp', 'unit', 'geo', 'flow_type', 'year', 'value']
+    assert sorted(zip(written['year'], written['value'])) == sorted(zip(expected['year'], expected['value']))
+    assert set(written['flow_type']) == {'Total Imports'}
+
+def test_write_flows(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    expected = parser.flows_frame(parser.get_flow_data(2019))
+    
+    with tempfile.TemporaryDirectory() as tmpdir:
+        csv_path = os.path.join(tmpdir, 'flows.csv')
+        assert parser.write_flows(2019, csv_path) == len(expected)
+        pd.testing.assert_frame_equal(pd.read_csv(csv_path), expected)
+        
+        json_path = os.path.join(tmpdir, 'flows.out')
+        assert parser.write_flows(2019, json_path, format='json', geo='AT') == 2
+        with open(json_path) as f:
+            assert [record['value'] for record in json.load(f)] == [100.5, 300.5]
+        
+        with pytest.raises(ValueError, match="csv, json, parquet"):
+            parser.write_flows(2019, os.path.join(tmpdir, 'flows.xlsx'))
+
+def test_get_flow_data_with_nace_labels(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    assert nace_label('C10-C12') == 'Manufacture of food products, beverages and tobacco products'
+    assert nace_label('B') == 'Mining and quarrying'
+    assert nace_label('X99') == 'X99'
+    
+    flows = parser.get_flow_data(2019, with_nace_labels=True)
+    assert [f['label'] for f in flows] == [f['source'] for f in flows]  # B01 and B02 are not FIGARO codes
+    
+    aggregated = parser.get_flow_data(2019, nace_level=1, with_nace_labels=True)
+    assert {f['label'] for f in aggregated} == {'Mining and quarrying'}
+
+def test_estimated_memory(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    cached = parser._read_and_clean_df(parser.imports_file)
+    
+    assert parser.estimated_memory(parser.imports_file) == int(cached.memory_usage(deep=True).sum())
+    assert parser.estimated_memory(parser.imports_file, categorical=False) > 0
+
+def test_series_count(sample_data_dir, flagged_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    assert parser.series_count(parser.imports_file) == 4
+    
+    flagged = FIGAROParser(flagged_data_dir)
+    assert flagged.series_count(flagged.exports_file) == 3
+
+def test_describe_schema(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    assert parser.describe_schema(parser.imports_file) == [
+        ('freq', 'object'),
+        ('nace_r2', 'object'),
+        ('c_exp', 'object'),
+        ('unit', 'object'),
+        ('geo', 'object'),
+        ('2019', 'float64'),
+        ('2020', 'float64')
+    ]
+    
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t2020\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t10\t2.5\n"
+            "A,B02,EXP_GO,MIO_EUR,AT\t20\t3\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        whole = FIGAROParser(tmpdir)
+        assert whole.describe_schema(whole.imports_file)[-2:] == [('2019', 'Int64'), ('2020', 'float64')]
+
+@pytest.mark.parametrize('policy, expected', [
+    ('first', [100.5]),
+    ('last', [200.5]),
+    ('sum', [301.0])
+])
+def test_duplicate_year_columns(policy, expected):
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t2020\t2020\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t1.0\t100.5\t200.5\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        
+        with pytest.raises(SchemaMismatchError, match="Repeated period columns: 2020"):
+            FIGAROParser(tmpdir).get_flow_data(2020)
+        
+        parser = FIGAROParser(tmpdir, on_duplicate_year=policy)
+        assert parser.get_available_years() == [2019, 2020]
+        assert [f['value'] for f in parser.get_flow_data(2020, include_exports=False)] == expected
+
+def test_large_integers_stay_exact():
+    big = 2 ** 53 + 1
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t2020\n"
+            f"A,B01,EXP_GO,MIO_EUR,AT\t{big}\t{big}.5\n"
+            "A,B02,EXP_GO,MIO_EUR,BE\t:\t2\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        
+        frame = parser.read_frame(parser.imports_file)
+        assert str(frame['2019'].dtype) == 'Int64'
+        assert frame['2019'][0] == big
+        assert pd.isna(frame['2019'][1])
+        assert frame['2020'].dtype == 'float64'
+        
+        flows = parser.get_flow_data(2019, include_exports=False)
+        assert [f['value'] for f in flows] == [big]
+        assert isinstance(flows[0]['value'], int)
+        assert parser.get_flow_data(2019, include_exports=False, scale=1000)[0]['value'] == pytest.approx(big / 1000)
+
+def test_get_flow_data_freq_filter():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\t2019M01\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t120\t:\n"
+            "M,B01,EXP_GO,MIO_EUR,AT\t10\t10\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        
+        assert [f['value'] for f in parser.get_flow_data(2019, include_exports=False)] == [120, 10]
+        assert [f['value'] for f in parser.get_flow_data(2019, include_exports=False, freq_filter='A')] == [120]
+        with pytest.raises(ValueError, match="Frequency Q not found"):
+            parser.get_flow_data(2019, freq_filter='Q')
+
+def test_metadata_fields_are_trimmed():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A, B01 ,EXP_GO,MIO_EUR, DE\t100.5\n"
+            "A,B02,EXP_GO, MIO_EUR ,FR \t50.5\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        
+        assert parser.get_available_regions() == ['DE', 'FR']
+        flows = parser.get_flow_data(2019, geo='DE', include_exports=False)
+        assert [(f['source'], f['unit'], f['value']) for f in flows] == [('B01', 'MIO_EUR', 100.5)]
+
+def test_get_flow_data_with_options(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    
+    assert parser.get_flow_data_with_options(2019) == parser.get_flow_data(2019)
+    assert parser.get_flow_data_with_options(2019, {'geo': 'AT', 'scale': 10}) == (
+        parser.get_flow_data(2019, geo='AT', scale=10)
+    )
+    assert parser.get_flow_data_with_options(2019, FlowOptions(top_n=1)) == parser.get_flow_data(2019, top_n=1)
+    
+    with pytest.raises(ValueError, match="Unknown flow options: country"):
+        parser.get_flow_data_with_options(2019, {'country': 'AT'})
+
+def test_version_info():
+    info = parser_module.version_info()
+    assert info['version'] == parser_module.__version__
+    assert info['pandas'] == pd.__version__
+    assert info['features']['gzip'] is True
+    assert set(info['features']) == {'gzip', 'zip', 'http', 'streaming', 'parquet', 'arrow'}
+
+def test_get_flow_data_custom_labels(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    flows = parser.get_flow_data(2019, import_label='Importaciones', export_label='Exportaciones')
+    
+    assert [f['target'] for f in flows] == ['Importaciones'] * 2 + ['Exportaciones'] * 2
+    assert [f['value'] for f in flows] == [f['value'] for f in parser.get_flow_data(2019)]
+
+def test_memory_map_matches_buffered_read(sample_data_dir):
+    expected = FIGAROParser(sample_data_dir).get_flow_data(2019)
+    parser = FIGAROParser(sample_data_dir, memory_map=True)
+    
+    assert parser._csv_options(parser.imports_file)['memory_map'] is True
+    parser_module.clear_tsv_cache()
+    assert parser.get_flow_data(2019) == expected
+
+def test_memory_map_skips_gzip_and_bytes(sample_data_dir):
+    imports_file = os.path.join(sample_data_dir, 'figaro', 'estat_naio_10_fgti.tsv')
+    with open(imports_file, 'rb') as f:
+        data = f.read()
+    parser = FIGAROParser(sample_data_dir, memory_map=True)
+    
+    assert parser._csv_options(data)['memory_map'] is False
+    assert parser._csv_options(gzip.compress(data))['memory_map'] is False
+
+def test_iter_flow_data(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    flows = parser.iter_flow_data(2019, chunksize=1)
+    
+    assert next(flows) == parser.get_flow_data(2019)[0]
+    assert [next(flows)] + list(flows) == parser.get_flow_data(2019)[1:]
+    assert list(parser.iter_flow_data(2019, min_value=200.0)) == parser.get_flow_data(2019, min_value=200.0)
+    with pytest.raises(MissingYearError):
+        next(parser.iter_flow_data(1999))
+
+def test_get_flow_data_sort_by(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    
+    by_value = parser.get_flow_data(2019, sort_by='value')
+    assert [f['value'] for f in by_value] == [350.3, 300.5, 150.3, 100.5]
+    assert [f['value'] for f in parser.get_flow_data(2019, sort_by='value', descending=False)] == [100.5, 150.3, 300.5, 350.3]
+    
+    by_nace = parser.get_flow_data(2019, sort_by='nace', descending=False)
+    assert [(f['source'], f['target']) for f in by_nace] == [
+        ('B01', 'Total Exports'), ('B01', 'Total Imports'), ('B02', 'Total Exports'), ('B02', 'Total Imports')
+    ]
+    by_flow_type = parser.get_flow_data(2019, sort_by='flow_type')
+    assert [(f['target'], f['source']) for f in by_flow_type] == [
+        ('Total Imports', 'B01'), ('Total Imports', 'B02'), ('Total Exports', 'B01'), ('Total Exports', 'B02')
+    ]
+    
+    kept = parser.get_flow_data(2019, sort_by='value', nan_mode='keep')
+    assert kept[-1]['value'] is None and kept[-1]['source'] == 'B04'
+    with pytest.raises(ValueError, match="sort_by"):
+        parser.get_flow_data(2019, sort_by='geo')
+
+def test_get_flow_data_grouped(sample_data_dir):
+    parser = FIGAROParser(sample_data_dir)
+    grouped = parser.get_flow_data_grouped(2019)
+    
+    assert grouped == {
+        'imports': [('B02', 150.3), ('B01', 100.5)],
+        'exports': [('B02', 350.3), ('B01', 300.5)],
+    }
+    assert parser.get_flow_data_grouped(2019, include_exports=False)['exports'] == []
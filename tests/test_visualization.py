@@ -0,0 +1,43 @@
+import pytest
+import tempfile
+from src.errors import MixedUnitsError
+from src.parser import FIGAROParser
+from src.visualization import sankey_edges
+from tests.test_parser import write_figaro_files
+
+@pytest.fixture
+def edge_parser():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        import_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t100\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t50\n"
+            "A,B02,EXP_GO,MIO_EUR,AT\t0\n"
+        )
+        export_content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,IMP_GO,MIO_EUR,DE\t20\n"
+            "A,B02,IMP_GO,MIO_EUR,DE\t:\n"
+        )
+        write_figaro_files(tmpdir, import_content, export_content)
+        yield FIGAROParser(tmpdir)
+
+def test_sankey_edges(edge_parser):
+    assert sankey_edges(edge_parser, 2019) == [
+        ('AT', 'B01', 150.0),
+        ('B01', 'DE', 20.0)
+    ]
+
+def test_sankey_edges_mixed_units():
+    with tempfile.TemporaryDirectory() as tmpdir:
+        content = (
+            "freq,nace_r2,c_exp,unit,geo\t2019\n"
+            "A,B01,EXP_GO,MIO_EUR,AT\t100\n"
+            "A,B01,EXP_GO,THS_T,AT\t7\n"
+        )
+        write_figaro_files(tmpdir, content, content)
+        parser = FIGAROParser(tmpdir)
+        
+        with pytest.raises(MixedUnitsError):
+            sankey_edges(parser, 2019)
+        assert sankey_edges(parser, 2019, unit_filter='MIO_EUR') == [('AT', 'B01', 100.0), ('B01', 'AT', 100.0)]